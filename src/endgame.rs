@@ -0,0 +1,384 @@
+// Exact endgame search. Once the bag is empty a player can work out the
+// opponent's rack from the unseen tiles, so the rest of the game can be
+// searched like chess: negamax with alpha-beta pruning over every play and
+// pass, with a transposition table for positions reached by different orders
+// of the same plays. The search goes one ply deeper at a time until every
+// line reaches the end of the game, so that it always has a line to play if
+// it runs out of positions to look at first.
+//
+// Values are spreads from the point of view of the player to move, counting
+// the end-of-game rack adjustments: going out collects the opponent's rack
+// and is worth it twice over, while a game that ends on scoreless turns costs
+// each player their own rack.
+
+use std::collections::HashMap;
+
+use crate::equity::leave_after;
+use crate::game::{blank_for, Board, Rack, RACK_SIZE};
+use crate::greedy::{find_all_words, find_greediest_word, WordChoice};
+use crate::session::MAX_SCORELESS_TURNS;
+use crate::sim::unseen_tiles;
+
+// A second or two of searching. That sees the end of the game once the racks
+// are small, but full racks on both sides can need far more.
+pub const DEFAULT_NODES: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndgameConfig {
+    // how many plies deep to search at most. None searches to the end.
+    pub max_plies: Option<usize>,
+    // positions to look at before settling for the deepest finished search.
+    pub max_nodes: usize,
+}
+
+impl Default for EndgameConfig {
+    fn default() -> Self {
+        EndgameConfig { max_plies: None, max_nodes: DEFAULT_NODES }
+    }
+}
+
+// A turn in the principal variation. None is a pass.
+pub type EndgameMove = Option<WordChoice>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EndgameResult {
+    // final spread for the player to move, relative to the current scores.
+    pub spread: i32,
+    // the best line for both sides, starting with the player to move.
+    pub line: Vec<EndgameMove>,
+    // how deep the search went, and whether that was to the end of the game
+    // on every line. If not, the spread is partly an estimate.
+    pub plies: usize,
+    pub exact: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    // the value is at least this.
+    Lower,
+    // the value is at most this.
+    Upper,
+}
+
+struct Entry {
+    // plies searched below this position.
+    remaining: usize,
+    value: i32,
+    bound: Bound,
+    line: Vec<EndgameMove>,
+}
+
+// board, rack to move, other rack, scoreless turns so far.
+type Key = (String, Vec<char>, Vec<char>, u32);
+
+struct Position<'a> {
+    board: &'a Board,
+    // the rack of the player to move.
+    rack: &'a Rack,
+    opponent: &'a Rack,
+    scoreless: u32,
+}
+
+pub struct EndgameSolver {
+    config: EndgameConfig,
+    table: HashMap<Key, Entry>,
+    nodes: usize,
+    // stop once this many positions have been visited.
+    node_limit: usize,
+    // the current search stopped short of the end of the game somewhere.
+    cut_off: bool,
+    // the current search ran out of positions and is thrown away.
+    out_of_nodes: bool,
+}
+
+impl EndgameSolver {
+    pub fn new(config: EndgameConfig) -> Self {
+        EndgameSolver {
+            config,
+            table: HashMap::new(),
+            nodes: 0,
+            node_limit: usize::MAX,
+            cut_off: false,
+            out_of_nodes: false,
+        }
+    }
+
+    // positions visited by the last solve.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    // scoreless_turns is how many turns in a row have gone without points, so
+    // that the search knows when passing ends the game.
+    pub fn solve(&mut self, board: &Board, rack: &Rack, opponent: &Rack, scoreless_turns: u32) -> EndgameResult {
+        self.nodes = 0;
+        let position = Position { board, rack, opponent, scoreless: scoreless_turns };
+        let max_plies = self.config.max_plies.unwrap_or(usize::MAX).max(1);
+
+        let mut result = None;
+        for plies in 1..=max_plies {
+            self.table.clear();
+            self.cut_off = false;
+            self.out_of_nodes = false;
+            // the first search always finishes, so that there is a line to play
+            self.node_limit = if plies == 1 { usize::MAX } else { self.config.max_nodes };
+
+            let (spread, line) = self.negamax(&position, plies, -i32::MAX, i32::MAX);
+            if self.out_of_nodes {
+                break;
+            }
+            let exact = !self.cut_off;
+            result = Some(EndgameResult { spread, line, plies, exact });
+            if exact {
+                break;
+            }
+        }
+        result.expect("The first search always finishes.")
+    }
+
+    fn negamax(&mut self, position: &Position, remaining: usize, mut alpha: i32, beta: i32) -> (i32, Vec<EndgameMove>) {
+        self.nodes += 1;
+        if self.nodes > self.node_limit {
+            self.out_of_nodes = true;
+            return (0, Vec::new());
+        }
+        let &Position { board, rack, opponent, scoreless } = position;
+
+        if scoreless >= MAX_SCORELESS_TURNS {
+            // both players lose what is left on their racks
+            return (opponent.value(board.rules()) - rack.value(board.rules()), Vec::new());
+        }
+        if remaining == 0 {
+            self.cut_off = true;
+            return (estimate(board, rack, opponent), Vec::new());
+        }
+
+        let key = (board_key(board), rack.get_tiles_vec(), opponent.get_tiles_vec(), scoreless);
+        let original_alpha = alpha;
+        if let Some(entry) = self.table.get(&key)
+            && entry.remaining >= remaining
+        {
+            match entry.bound {
+                Bound::Exact => return (entry.value, entry.line.clone()),
+                Bound::Lower if entry.value >= beta => return (entry.value, entry.line.clone()),
+                Bound::Upper if entry.value <= alpha => return (entry.value, entry.line.clone()),
+                _ => {}
+            }
+        }
+
+        // try plays that go out and big plays first, so that cutoffs come early
+        let mut plays = find_all_words(board, rack).into_vec();
+        plays.sort_by_key(|play| std::cmp::Reverse((play.tiles.len() == rack.len(), play.score)));
+
+        let mut best = -i32::MAX;
+        let mut best_line = Vec::new();
+
+        for play in plays.into_iter().map(Some).chain([None]) {
+            let (value, line) = match &play {
+                Some(choice) => {
+                    let mut board = board.clone();
+                    let mut rack = rack.clone();
+                    let placed = if choice.across {
+                        board.write_across_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col)
+                    } else {
+                        board.write_down_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col)
+                    };
+                    let Ok(score) = placed.and_then(|()| board.submit()) else {
+                        continue;
+                    };
+
+                    if rack.is_empty() {
+                        (score + 2 * opponent.value(board.rules()), Vec::new())
+                    } else {
+                        // a play keeps the game going even if it scores nothing
+                        let child = Position { board: &board, rack: opponent, opponent: &rack, scoreless: 0 };
+                        let (value, line) = self.negamax(
+                            &child,
+                            remaining - 1,
+                            score.saturating_sub(beta),
+                            score.saturating_sub(alpha),
+                        );
+                        (score - value, line)
+                    }
+                }
+                None => {
+                    let child = Position { board, rack: opponent, opponent: rack, scoreless: scoreless + 1 };
+                    let (value, line) = self.negamax(&child, remaining - 1, -beta, -alpha);
+                    (-value, line)
+                }
+            };
+            if self.out_of_nodes {
+                return (0, Vec::new());
+            }
+
+            if value > best {
+                best = value;
+                best_line = std::iter::once(play).chain(line).collect();
+            }
+            alpha = alpha.max(best);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(key, Entry { remaining, value: best, bound, line: best_line.clone() });
+
+        (best, best_line)
+    }
+}
+
+// A guess at the value of a position the search stops short of: the player to
+// move makes their best scoring play, going out if that uses every tile, and
+// then both players are left with their racks. Passing until the game ends is
+// the least they can do.
+fn estimate(board: &Board, rack: &Rack, opponent: &Rack) -> i32 {
+    let rules = board.rules();
+    let stuck = opponent.value(rules) - rack.value(rules);
+
+    let Some(best) = find_greediest_word(board, rack) else {
+        return stuck;
+    };
+    let mut left = Rack::new();
+    for tile in leave_after(&rack.get_tiles_vec(), &best.tiles) {
+        left.add_tile(tile);
+    }
+    if left.is_empty() {
+        return best.score + 2 * opponent.value(rules);
+    }
+    stuck.max(best.score + opponent.value(rules) - left.value(rules))
+}
+
+// The opponent's rack once the bag is empty, if it can be worked out. With
+// more than one opponent the unseen tiles could be on any of their racks.
+pub fn deduce_opponent_rack(board: &Board, rack: &Rack, bag_size: usize, opponents: usize) -> Option<Rack> {
+    if bag_size > 0 || opponents != 1 {
+        return None;
+    }
+
+    // more than a rack's worth means the tiles were not all drawn the usual way
+    let unseen = unseen_tiles(board, rack);
+    if unseen.len() > RACK_SIZE {
+        return None;
+    }
+
+    let mut opponent = Rack::new();
+    for tile in unseen {
+        opponent.add_tile(tile);
+    }
+    Some(opponent)
+}
+
+fn board_key(board: &Board) -> String {
+    let mut key = String::with_capacity(board.size() * board.size());
+    for row in 0..board.size() {
+        for col in 0..board.size() {
+            let tile = board.get_tile(row, col);
+            key.push(if board.is_blank(row, col) { blank_for(tile) } else { tile });
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::lexicon::Lexicon;
+
+    fn board(words: &[&str]) -> Board {
+        let mut board = Board::with_lexicon(Arc::new(Lexicon::from_words(words)));
+        board.write_across(String::from("CAT"), 7, 6).unwrap();
+        board.submit().unwrap();
+        board
+    }
+
+    #[test]
+    fn test_goes_out() {
+        let board = board(&["CAT", "AT", "TA", "CATS"]);
+
+        // S goes out for 6 and collects the opponent's Q twice over
+        let result = EndgameSolver::new(EndgameConfig::default()).solve(&board, &Rack::from_tiles("S"), &Rack::from_tiles("Q"), 0);
+        assert_eq!(result.spread, 6 + 2 * 10);
+        assert_eq!(result.line.len(), 1);
+        assert_eq!(result.line[0].as_ref().unwrap().word, "CATS");
+    }
+
+    #[test]
+    fn test_stuck() {
+        let board = board(&["CAT"]);
+
+        // nobody can play, so the game ends on passes with both racks lost
+        let result = EndgameSolver::new(EndgameConfig::default()).solve(&board, &Rack::from_tiles("Q"), &Rack::from_tiles("ZE"), 0);
+        assert_eq!(result.spread, 11 - 10);
+        assert!(result.line.iter().all(|play| play.is_none()));
+        assert_eq!(result.line.len(), MAX_SCORELESS_TURNS as usize);
+        assert!(result.exact);
+    }
+
+    #[test]
+    fn test_pass_ends_the_game() {
+        let board = Board::with_lexicon(Arc::new(Lexicon::from_words(["QI"])));
+        let rack = Rack::from_tiles("VV");
+        let opponent = Rack::from_tiles("QI");
+
+        // stuck with VV, so the opponent goes out with QI after a pass
+        let result = EndgameSolver::new(EndgameConfig::default()).solve(&board, &rack, &opponent, 0);
+        assert_eq!(result.line.len(), 2);
+        assert_eq!(result.spread, -(22 + 2 * 8));
+
+        // unless the pass is the last scoreless turn, when both racks are lost
+        let result = EndgameSolver::new(EndgameConfig::default()).solve(&board, &rack, &opponent, MAX_SCORELESS_TURNS - 1);
+        assert_eq!(result.line, vec![None]);
+        assert_eq!(result.spread, 11 - 8);
+        assert!(result.exact);
+    }
+
+    #[test]
+    fn test_blocks_the_opponent() {
+        // Both players want an S hook on CAT. Whoever takes one first leaves
+        // the other stuck, since SCATS is not a word.
+        let board = board(&["CAT", "CATS", "SCAT", "AT", "TA", "ATE", "EAT", "TEA"]);
+
+        let mut solver = EndgameSolver::new(EndgameConfig::default());
+        let result = solver.solve(&board, &Rack::from_tiles("SE"), &Rack::from_tiles("SQ"), 0);
+        let first = result.line[0].as_ref().unwrap();
+        assert_eq!(first.tiles, vec!['S']);
+        assert_eq!(result.spread, 6 + (1 + 10) - 1);
+        assert!(solver.nodes() > 0);
+    }
+
+    #[test]
+    fn test_sets_up_going_out() {
+        // CAB down from the C, then BED across from the B. Neither play can
+        // go out alone and BED needs the B, so going out takes three plies
+        // with the opponent's pass in between.
+        let board = board(&["CAT", "CAB", "BED"]);
+
+        let result = EndgameSolver::new(EndgameConfig::default()).solve(&board, &Rack::from_tiles("ABDE"), &Rack::from_tiles("Q"), 0);
+        assert!(result.exact);
+        let words: Vec<_> = result.line.iter().map(|play| play.as_ref().map(|play| play.word.as_str())).collect();
+        assert_eq!(words, vec![Some("CAB"), None, Some("BED")]);
+        let scores: i32 = result.line.iter().flatten().map(|play| play.score).sum();
+        assert_eq!(result.spread, scores + 2 * 10);
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let board = board(&["CAT", "CAB", "BED"]);
+
+        // too few positions to see the end, so the one-ply search is played
+        let config = EndgameConfig { max_nodes: 1, ..EndgameConfig::default() };
+        let result = EndgameSolver::new(config).solve(&board, &Rack::from_tiles("ABDE"), &Rack::from_tiles("Q"), 0);
+        assert_eq!(result.plies, 1);
+        assert!(!result.exact);
+        assert_eq!(result.line.len(), 1);
+    }
+}
@@ -0,0 +1,258 @@
+// Static move evaluation. A play's equity is its score plus the value of the
+// tiles it leaves on the rack, so that a few points are not worth keeping
+// UUVW or throwing away a blank.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::game::{Board, Rack};
+use crate::greedy::{find_all_words, WordChoice};
+use crate::player::MIN_BAG_FOR_SWAP;
+use crate::rules::Rules;
+
+// Rough values of keeping each tile. Used for any leave the table does not list.
+const TILE_VALUES: [(char, f64); 27] = [
+    ('A', 1.0), ('B', -2.0), ('C', 0.9), ('D', 0.5), ('E', 1.5), ('F', -2.2),
+    ('G', -2.9), ('H', 1.1), ('I', -0.5), ('J', -1.4), ('K', -0.5), ('L', -0.2),
+    ('M', 0.6), ('N', 0.2), ('O', -2.5), ('P', -0.5), ('Q', -6.8), ('R', 1.1),
+    ('S', 7.6), ('T', -0.1), ('U', -5.1), ('V', -5.5), ('W', -3.8), ('X', 3.3),
+    ('Y', -0.6), ('Z', 5.1), ('*', 24.0),
+];
+
+// Keeping two of the same tile makes bingos less likely.
+const DUPLICATE_PENALTY: f64 = 3.0;
+// for each vowel or consonant more than one past the other.
+const IMBALANCE_PENALTY: f64 = 1.5;
+
+// What a leave is worth, keyed by its tiles in sorted order with '*' for
+// blanks.
+#[derive(Clone, Debug)]
+pub struct LeaveTable {
+    values: HashMap<String, f64>,
+}
+
+impl Default for LeaveTable {
+    fn default() -> Self {
+        let values = TILE_VALUES.iter().map(|&(tile, value)| (tile.to_string(), value)).collect();
+        LeaveTable { values }
+    }
+}
+
+impl LeaveTable {
+    // Reads a table with one leave per line followed by its value, e.g.
+    // "ERS 11.5". Blanks may be written as '?' or '*' and '#' starts a
+    // comment. Single tiles missing from the file keep their default values.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut table = LeaveTable::default();
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let bad_line = || {
+                io::Error::new(io::ErrorKind::InvalidData, format!("bad leave on line {}: '{line}'", number + 1))
+            };
+            let (leave, value) = line.split_once(char::is_whitespace).ok_or_else(bad_line)?;
+            let value: f64 = value.trim().parse().map_err(|_| bad_line())?;
+            let tiles: Vec<char> = leave
+                .chars()
+                .map(|tile| if tile == '?' { '*' } else { tile.to_uppercase().next().unwrap_or(tile) })
+                .collect();
+            if !tiles.iter().all(|&tile| tile == '*' || tile.is_uppercase()) {
+                return Err(bad_line());
+            }
+
+            table.values.insert(key(tiles), value);
+        }
+
+        Ok(table)
+    }
+
+    pub fn value(&self, leave: &[char]) -> f64 {
+        if leave.is_empty() {
+            return 0.0;
+        }
+        if let Some(&value) = self.values.get(&key(leave.to_vec())) {
+            return value;
+        }
+
+        // not listed, so estimate from the single tiles
+        let mut sorted = leave.to_vec();
+        sorted.sort();
+        let mut value = 0.0;
+        for (i, tile) in sorted.iter().enumerate() {
+            value += self.values.get(&tile.to_string()).copied().unwrap_or(0.0);
+            if i > 0 && sorted[i - 1] == *tile && *tile != '*' {
+                value -= DUPLICATE_PENALTY;
+            }
+        }
+
+        let vowels = leave.iter().filter(|tile| "AEIOU".contains(**tile)).count() as f64;
+        let consonants = leave.iter().filter(|&&tile| tile != '*').count() as f64 - vowels;
+        value -= IMBALANCE_PENALTY * ((vowels - consonants).abs() - 1.0).max(0.0);
+
+        value
+    }
+}
+
+fn key(mut tiles: Vec<char>) -> String {
+    tiles.sort();
+    tiles.into_iter().collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decision {
+    Play(WordChoice),
+    // the tiles to put back in the bag.
+    Exchange(Vec<char>),
+    Pass,
+}
+
+// the rack left after taking tiles off it. Blanks are '*' in both.
+pub fn leave_after(rack: &[char], tiles: &[char]) -> Vec<char> {
+    let mut leave = rack.to_vec();
+    for tile in tiles {
+        if let Some(i) = leave.iter().position(|t| t == tile) {
+            leave.remove(i);
+        }
+    }
+    leave
+}
+
+// Score plus leave. Once the bag is empty nothing will be drawn, so the leave
+// only counts for the points it will cost at the end of the game.
+pub fn equity(score: i32, leave: &[char], bag_size: usize, leaves: &LeaveTable, rules: &Rules) -> f64 {
+    if bag_size == 0 {
+        let stuck: i32 = leave.iter().map(|&tile| rules.tile_set.score_letter(tile)).sum();
+        score as f64 - 2.0 * stuck as f64
+    } else {
+        score as f64 + leaves.value(leave)
+    }
+}
+
+// The play or exchange with the highest equity. Passes only when there is
+// nothing else to do.
+pub fn choose_move(board: &Board, rack: &Rack, bag_size: usize, leaves: &LeaveTable) -> Decision {
+    let tiles = rack.get_tiles_vec();
+    let mut best = Decision::Pass;
+    let mut best_equity = f64::NEG_INFINITY;
+
+    for choice in find_all_words(board, rack) {
+        let value = equity(choice.score, &leave_after(&tiles, &choice.tiles), bag_size, leaves, board.rules());
+        if value > best_equity {
+            best_equity = value;
+            best = Decision::Play(choice);
+        }
+    }
+
+    if bag_size >= MIN_BAG_FOR_SWAP {
+        for kept in sub_racks(&tiles) {
+            if kept.len() == tiles.len() {
+                continue; // that would be a pass
+            }
+            let value = equity(0, &kept, bag_size, leaves, board.rules());
+            if value > best_equity {
+                best_equity = value;
+                best = Decision::Exchange(leave_after(&tiles, &kept));
+            }
+        }
+    }
+
+    best
+}
+
+// every distinct selection of tiles from a sorted rack.
+fn sub_racks(tiles: &[char]) -> Vec<Vec<char>> {
+    let mut result = vec![Vec::new()];
+    let mut i = 0;
+    while i < tiles.len() {
+        let tile = tiles[i];
+        let count = tiles[i..].iter().take_while(|&&t| t == tile).count();
+
+        let mut extended = Vec::new();
+        for sub in &result {
+            for n in 0..=count {
+                let mut sub = sub.clone();
+                sub.extend(std::iter::repeat_n(tile, n));
+                extended.push(sub);
+            }
+        }
+        result = extended;
+        i += count;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::lexicon::Lexicon;
+
+    #[test]
+    fn test_leave_values() {
+        let leaves = LeaveTable::default();
+        assert!(leaves.value(&['S']) > leaves.value(&['U']));
+        assert!(leaves.value(&['*', 'E', 'R', 'S']) > 0.0);
+        assert!(leaves.value(&['U', 'U', 'V', 'W']) < leaves.value(&['U', 'V', 'W']));
+        assert_eq!(leaves.value(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_table_file() {
+        let path = std::env::temp_dir().join(format!("words-leaves-{}.txt", std::process::id()));
+        fs::write(&path, "# leave value\nSRE 12.5\n?s 40\nQ -20\n").unwrap();
+        let leaves = LeaveTable::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(leaves.value(&['E', 'R', 'S']), 12.5);
+        assert_eq!(leaves.value(&['S', '*']), 40.0);
+        assert_eq!(leaves.value(&['Q']), -20.0);
+        assert_eq!(leaves.value(&['Z']), 5.1);
+    }
+
+    #[test]
+    fn test_sub_racks() {
+        assert_eq!(sub_racks(&['A', 'A', 'B']).len(), 6);
+        assert_eq!(sub_racks(&['A', 'B', 'C', 'D', 'E', 'F', 'G']).len(), 128);
+    }
+
+    #[test]
+    fn test_keeps_the_s() {
+        let lexicon = Arc::new(Lexicon::from_words(["CAT", "CATS"]));
+        let board = Board::with_lexicon(lexicon);
+        let rack = Rack::from_tiles("CATSEIR");
+
+        // CATS scores 12 to CAT's 10, but CAT keeps the S for later
+        let greedy = crate::greedy::find_greediest_word(&board, &rack).unwrap();
+        assert_eq!(greedy.word, "CATS");
+        assert_eq!(greedy.score, 12);
+
+        match choose_move(&board, &rack, 50, &LeaveTable::default()) {
+            Decision::Play(choice) => {
+                assert_eq!(choice.word, "CAT");
+                assert_eq!(choice.score, 10);
+                assert!(!choice.tiles.contains(&'S'));
+                assert_ne!(choice, greedy);
+            }
+            other => panic!("expected a play, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_exchanges_a_bad_rack() {
+        let lexicon = Arc::new(Lexicon::from_words(["UH"]));
+        let board = Board::with_lexicon(lexicon);
+
+        match choose_move(&board, &Rack::from_tiles("UUUVVWQ"), 50, &LeaveTable::default()) {
+            Decision::Exchange(tiles) => assert!(tiles.contains(&'Q')),
+            other => panic!("expected an exchange, got {other:?}"),
+        }
+        assert_eq!(choose_move(&board, &Rack::from_tiles("UUUVVWQ"), 3, &LeaveTable::default()), Decision::Pass);
+    }
+}
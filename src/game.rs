@@ -1,60 +1,150 @@
-use std::fmt::Write;
+use std::fmt::{self, Write};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use std::fs::File;
-use std::path::Path;
-use std::io::{self, BufRead};
 use std::collections::HashSet;
+use std::sync::Arc;
+use crate::layout::{Layout, Premium};
+use crate::lexicon::Lexicon;
+use crate::rules::Rules;
+use crate::tileset::TileSet;
+
+// tiles a rack is refilled to after each turn.
+pub const RACK_SIZE: usize = 7;
+
+// How a letter is written once a blank stands in for it.
+pub fn blank_for(letter: char) -> char {
+    letter.to_lowercase().next().unwrap_or(letter)
+}
+
+// the letter a tile was played as, whether or not it was a blank.
+pub fn letter_of(tile: char) -> char {
+    tile.to_uppercase().next().unwrap_or(tile)
+}
+
+// the tile a rack needs in order to play tile.
+pub fn rack_tile(tile: char) -> char {
+    if tile.is_lowercase() {
+        '*'
+    } else {
+        tile
+    }
+}
+
+// Players without a rack can play whatever they like, so give them a rack
+// holding exactly the tiles they asked for.
+pub fn rack_for(tiles: &str) -> Rack {
+    let mut rack = Rack::new();
+    for tile in tiles.chars() {
+        rack.add_tile(rack_tile(tile));
+    }
+    rack
+}
 
-const LETTER_SCORES: [i32; 26] = [1, 3, 3, 2, 1, 4, 2, 4, 1, 8, 5, 1, 3, 1, 1, 3, 10, 1, 1, 1, 1, 4, 4, 8, 4, 10];
+// Squares in the notation of real boards and game records: rows are numbered
+// from 1 and columns lettered from A. "8D" is row 7, column 3 across and "D8"
+// is the same square down.
+pub fn format_square(row: usize, col: usize, across: bool) -> String {
+    let col = column_name(col);
+    if across {
+        format!("{}{}", row + 1, col)
+    } else {
+        format!("{}{}", col, row + 1)
+    }
+}
+
+// Columns after Z go on AA, AB and so on, as in a spreadsheet.
+fn column_name(col: usize) -> String {
+    let mut name = Vec::new();
+    let mut rest = col + 1;
+    while rest > 0 {
+        name.push(b'A' + ((rest - 1) % 26) as u8);
+        rest = (rest - 1) / 26;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("Column names are ASCII.")
+}
+
+// the column column_name gives this name, whatever its case.
+fn column_number(name: &str) -> Option<usize> {
+    let number = name.bytes().try_fold(0usize, |number, letter| {
+        let letter = letter.to_ascii_uppercase();
+        if !letter.is_ascii_uppercase() {
+            return None;
+        }
+        number.checked_mul(26)?.checked_add((letter - b'A') as usize + 1)
+    })?;
+    number.checked_sub(1)
+}
 
-pub fn score_letter(letter: char) -> i32 {
-    if letter == '*' {
-        return 0;
+// the row, column and direction of a square written by format_square.
+pub fn parse_square(square: &str, size: usize) -> Result<(usize, usize, bool), String> {
+    let bad_square = || format!("bad square '{square}'");
+    let across = square.starts_with(|c: char| c.is_ascii_digit());
+    let split = if across {
+        square.find(|c: char| !c.is_ascii_digit())
+    } else {
+        square.find(|c: char| !c.is_ascii_alphabetic())
+    };
+    let Some(split) = split.filter(|&i| i > 0) else {
+        return Err(bad_square());
+    };
+    let (digits, letters) = if across {
+        (&square[..split], &square[split..])
+    } else {
+        (&square[split..], &square[..split])
+    };
+
+    let (Ok(row), Some(col)) = (digits.parse::<usize>(), column_number(letters)) else {
+        return Err(bad_square());
+    };
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(bad_square());
+    }
+    if row == 0 || row > size || col >= size {
+        return Err(format!("square '{square}' is off the board"));
     }
 
-    LETTER_SCORES[(letter as usize) - ('A' as usize)]
+    Ok((row - 1, col, across))
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bag {
     pub tiles: Vec<char>,
-    rng: rand::rngs::ThreadRng
+    // shuffles the bag, including after exchanges.
+    rng: StdRng,
+}
+
+impl Default for Bag {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bag {
     pub fn new() -> Self {
-        let mut tiles = vec![
-            'A','A','A','A','A','A','A','A','A',
-            'B','B',
-            'C','C',
-            'D','D','D','D',
-            'E','E','E','E','E','E','E','E','E','E','E','E',
-            'F','F',
-            'G','G','G',
-            'H','H',
-            'I','I','I','I','I','I','I','I','I',
-            'J',
-            'K',
-            'L','L','L','L',
-            'M','M',
-            'N','N','N','N','N','N',
-            'O','O','O','O','O','O','O','O',
-            'P','P',
-            'Q',
-            'R','R','R','R','R','R',
-            'S','S','S','S',
-            'T','T','T','T','T','T',
-            'U','U','U','U',
-            'V','V',
-            'W','W',
-            'X',
-            'Y','Y',
-            'Z',
-        ];
-
-        let mut rng = rand::rng();
+        Self::with_seed(rand::random())
+    }
+
+    // The same seed always gives the same draws, so that a game between bots
+    // can be played again.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rules(&Rules::standard(), seed)
+    }
+
+    // a full bag for these rules, shuffled from the seed.
+    pub fn with_rules(rules: &Rules, seed: u64) -> Self {
+        let mut tiles = rules.tile_set.tiles();
+        let mut rng = StdRng::seed_from_u64(seed);
         tiles.shuffle(&mut rng);
 
-        Bag { tiles: tiles, rng: rng }
+        Bag { tiles, rng }
+    }
+
+    // A bag holding exactly these tiles, in this order. Later shuffles always
+    // go the same way for the same tiles.
+    pub fn from_tiles(tiles: Vec<char>) -> Self {
+        Bag { tiles, rng: StdRng::seed_from_u64(0) }
     }
 
     pub fn draw(&mut self, rack: &mut Vec<char>, n: i32){
@@ -76,6 +166,12 @@ impl Bag {
         self.tiles.shuffle(&mut self.rng)
     }
 
+    // A seed from the bag's own shuffles, for a new bag that plays out the
+    // same way whenever this one does, e.g. one for a game loaded mid-game.
+    pub fn next_seed(&mut self) -> u64 {
+        self.rng.random()
+    }
+
     pub fn size(&self) -> usize {
         self.tiles.len()
     }
@@ -85,34 +181,62 @@ impl Bag {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Rack {
-    tiles: [usize; 128],
+    // how many of each tile there are, letters first and blanks last.
+    tiles: Vec<(char, usize)>,
     size: usize,
 }
 
+impl Default for Rack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the order tiles are listed in.
+fn rack_order(tile: char) -> (bool, char) {
+    (tile == '*', tile)
+}
+
 impl Rack {
     pub fn new() -> Self {
-        let tiles = [0; 128];
-        Rack { tiles: tiles, size: 0 }
+        Rack { tiles: Vec::new(), size: 0 }
     }
-    
+
     pub fn add_tile(&mut self, tile: char) {
-        self.tiles[tile as usize] += 1;
+        match self.tiles.binary_search_by_key(&rack_order(tile), |&(known, _)| rack_order(known)) {
+            Ok(i) => self.tiles[i].1 += 1,
+            Err(i) => self.tiles.insert(i, (tile, 1)),
+        }
         self.size += 1;
     }
 
     pub fn remove_tile(&mut self, tile: char) {
-        self.tiles[tile as usize] -= 1;
-        self.size -= 1;
+        assert!(self.has_tile(tile), "Tile {tile} is not in the rack.");
+        self.use_tile(tile);
+    }
+
+    // how many of this tile are on the rack.
+    pub fn count(&self, tile: char) -> usize {
+        self.tiles.iter().find(|&&(known, _)| known == tile).map_or(0, |&(_, count)| count)
     }
 
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
 
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    // the total face value of the tiles, used for end of game penalties.
+    pub fn value(&self, rules: &Rules) -> i32 {
+        self.get_tiles_vec().into_iter().map(|tile| rules.tile_set.score_letter(tile)).sum()
+    }
+
     pub fn draw(&mut self, bag: &mut Bag) {
-        while self.size < 7 {
+        while self.size < RACK_SIZE {
             let tile = bag.tiles.pop();
             match tile {
                 Some(c) => self.add_tile(c),
@@ -122,22 +246,13 @@ impl Rack {
     }
 
     pub fn get_tiles_vec(&self) -> Vec<char> {
-        let mut result = Vec::new();
-        for elt in 'A'..='Z' {
-            for _ in 0..self.tiles[elt as usize] {
-                result.push(elt);
-            }
-        }
-        for _ in 0..self.tiles['*' as usize] {
-            result.push('*');
-        }
-        result
+        self.tiles.iter().flat_map(|&(tile, count)| std::iter::repeat_n(tile, count)).collect()
     }
 
     pub fn swap(&mut self, bag: &mut Bag, tiles: Vec<char>) -> bool {
         let mut tiles_to_push = Vec::new();
         for tile in tiles {
-            if self.tiles[tile as usize] == 0 {
+            if !self.has_tile(tile) {
                 for replacement in tiles_to_push {
                     self.add_tile(replacement);
                 }
@@ -155,95 +270,141 @@ impl Rack {
         true
     }
 
-    pub fn show(&self) {
-        let mut result = Vec::new();
-        for elt in 'A'..='Z' {
-            for _ in 0..self.tiles[elt as usize] {
-                result.push(elt);
-            }
-        }
-        for _ in 0..self.tiles['*' as usize] {
-            result.push('*');
-        }
+    pub fn show(&self, tile_set: &TileSet) {
         print!("Rack: ");
-        for elt in result {
-            print!("{} ", elt);
+        for tile in self.get_tiles_vec() {
+            print!("{} ", tile_set.name(tile));
         }
-        print!("\n");
+        println!();
     }
 
-    pub fn has_tile(&mut self, tile: char) -> bool {
-        if self.tiles[tile as usize] == 0 {
-            return false;
-        }
-
-        true
+    pub fn has_tile(&self, tile: char) -> bool {
+        self.count(tile) > 0
     }
 
     pub fn use_tile(&mut self, tile: char) {
-        if self.tiles[tile as usize] == 0 {
+        let Some(i) = self.tiles.iter().position(|&(known, _)| known == tile) else {
             return;
+        };
+        self.tiles[i].1 -= 1;
+        if self.tiles[i].1 == 0 {
+            self.tiles.remove(i);
         }
-        self.tiles[tile as usize] -= 1;
         self.size -= 1;
     }
 }
 
+// Racks for tests, e.g. Rack::from_tiles("QI*") with a blank.
+#[cfg(test)]
+impl Rack {
+    pub fn from_tiles(tiles: &str) -> Self {
+        let mut rack = Rack::new();
+        for tile in tiles.chars() {
+            rack.add_tile(tile);
+        }
+        rack
+    }
+}
+
+// A play as it appears on the board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placement {
+    // the whole main word, with blanks in lowercase.
+    pub word: String,
+    pub row: usize,
+    pub col: usize,
+    pub across: bool,
+    // the newly placed tiles, with blanks in lowercase.
+    pub tiles: Vec<(usize, usize, char)>,
+}
+
+// Why tiles could not be placed or a play was not accepted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlacementError {
+    NoTilesStaged,
+    // the staged tiles are not all in one row or column.
+    NoFixedLine,
+    // there is a gap between the staged tiles.
+    NotContiguous,
+    // the play does not touch any tile already on the board.
+    NotConnected,
+    // the word as it is shown, with tiles like CH by name rather than by
+    // their stand-ins, so that the error can be printed as it is.
+    NotAWord(String),
+    // the name of the missing tile, e.g. "LL".
+    NotInRack(String),
+    OutOfBounds(usize, usize),
+    Occupied(usize, usize),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlacementError::NoTilesStaged => write!(f, "No tiles are staged."),
+            PlacementError::NoFixedLine => write!(f, "The tiles are not in a single row or column."),
+            PlacementError::NotContiguous => write!(f, "The tiles are not contiguous."),
+            PlacementError::NotConnected => write!(f, "The play does not touch the existing tiles."),
+            PlacementError::NotAWord(word) => write!(f, "{word} is not in the dictionary."),
+            PlacementError::NotInRack(tile) => write!(f, "Tile {tile} is not in the rack."),
+            PlacementError::OutOfBounds(row, col) => write!(f, "{} is off the board.", format_square(*row, *col, true)),
+            PlacementError::Occupied(row, col) => write!(f, "{} is already taken.", format_square(*row, *col, true)),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+#[derive(Clone)]
 pub struct Board {
     board: Vec<Vec<Space>>,
     staged_spaces: Vec<(usize, usize)>,
     neighbors: HashSet<(usize, usize)>,
-    word_list: Vec<String>,
-    partials_list: Vec<String>,
+    lexicon: Arc<Lexicon>,
+    rules: Arc<Rules>,
+    last_play: Option<Placement>,
 }
 
 impl Board {
-    pub fn new(dict_path: String, partials_path: String) -> Self {
-        let word_list = Board::read_word_list(dict_path).unwrap();
-        let partials_list = Board::read_word_list(partials_path).unwrap();
-
-        let id = Space{tile: '-', letter_mult: 1, word_mult: 1, val: 0};
-        let dl = Space{tile: '-', letter_mult: 2, word_mult: 1, val: 0};
-        let tl = Space{tile: '-', letter_mult: 3, word_mult: 1, val: 0};
-        let dw = Space{tile: '-', letter_mult: 1, word_mult: 2, val: 0};
-        let tw = Space{tile: '-', letter_mult: 1, word_mult: 3, val: 0};
-
-        let mut board: Vec<Vec<Space>> = Vec::new();
-
-        board.push(vec![tw.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), tw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), tw.clone()]);
-        board.push(vec![id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone()]);
-        board.push(vec![id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone()]);
-        board.push(vec![dl.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), dl.clone()]);
-        board.push(vec![id.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), id.clone()]);
-        board.push(vec![id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone()]);
-        board.push(vec![id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone()]);
-        board.push(vec![tw.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), tw.clone()]);
-        board.push(vec![id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone()]);
-        board.push(vec![id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone()]);
-        board.push(vec![id.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), id.clone()]);
-        board.push(vec![dl.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), dl.clone()]);
-        board.push(vec![id.clone(), id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone(), id.clone()]);
-        board.push(vec![id.clone(), dw.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), tl.clone(), id.clone(), id.clone(), id.clone(), dw.clone(), id.clone()]);
-        board.push(vec![tw.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), id.clone(), tw.clone(), id.clone(), id.clone(), id.clone(), dl.clone(), id.clone(), id.clone(), tw.clone()]);
+    pub fn new(dict_path: String) -> Self {
+        let lexicon = Lexicon::from_file(dict_path).unwrap();
+        Board::with_lexicon(Arc::new(lexicon))
+    }
+
+    // Boards built from the same word list can share a single lexicon.
+    pub fn with_lexicon(lexicon: Arc<Lexicon>) -> Self {
+        Board::with_rules(lexicon, Arc::new(Rules::standard()))
+    }
+
+    pub fn with_rules(lexicon: Arc<Lexicon>, rules: Arc<Rules>) -> Self {
+        let layout = &rules.layout;
+        let board = (0..layout.size())
+            .map(|row| {
+                (0..layout.size())
+                    .map(|col| {
+                        Space { tile: '-', blank: false, premium: layout.premium(row, col), val: 0 }
+                    })
+                    .collect()
+            })
+            .collect();
 
         let mut neighbors = HashSet::new();
-        neighbors.insert((7, 7));
+        neighbors.insert(layout.start);
 
-        Board { board: board, staged_spaces: Vec::new(), word_list: word_list, partials_list: partials_list, neighbors: neighbors }
+        Board { board, staged_spaces: Vec::new(), neighbors, lexicon, rules, last_play: None }
     }
 
-    fn read_word_list<P>(filename: P) -> io::Result<Vec<String>> where P: AsRef<Path>{
-        let file: File = File::open(filename)?;
-        let lines = io::BufReader::new(file).lines();
-        let mut word_list = Vec::new();
+    pub fn lexicon(&self) -> &Arc<Lexicon> {
+        &self.lexicon
+    }
 
-        for line in lines {
-            word_list.push(line?);
-        }
+    pub fn rules(&self) -> &Arc<Rules> {
+        &self.rules
+    }
 
-        Ok(word_list)
+    pub fn layout(&self) -> &Arc<Layout> {
+        &self.rules.layout
     }
-    
+
     pub fn get_board_chars(&self) -> Vec<Vec<char>> {
         let mut result = Vec::new();
         for row in &self.board {
@@ -256,12 +417,8 @@ impl Board {
         result
     }
 
-    pub fn substr_promising(&mut self, substring: &String) -> bool {
-        match self.partials_list.binary_search(substring) {
-            Ok(_n) => { return true; }
-            Err(n) => { if self.partials_list[n].starts_with(substring) { return true; }}
-        }
-        false
+    pub fn substr_promising(&self, substring: &str) -> bool {
+        self.lexicon.is_substring(substring)
     }
 
     pub fn get_neighbors(&self) -> Vec<(usize, usize)> {
@@ -272,24 +429,53 @@ impl Board {
         result
     }
 
-    pub fn put_tile_from_rack(&mut self, rack: &mut Rack, tile: char, row: usize, col: usize) {
-        if !rack.has_tile(tile) {
-            println!("Cannot put tile {tile}. Not in rack.");
-            return;
-        }
-        self.put_tile(tile, row, col);
-        rack.use_tile(tile);
+    pub fn is_neighbor(&self, row: usize, col: usize) -> bool {
+        self.neighbors.contains(&(row, col))
     }
 
-    pub fn put_tile(&mut self, tile: char, row: usize, col: usize) {
-        if self.board[row][col].tile != '-' {
-            return; // not empty
+    // a lowercase tile is played with a blank from the rack.
+    pub fn put_tile_from_rack(&mut self, rack: &mut Rack, tile: char, row: usize, col: usize) -> Result<(), PlacementError> {
+        if !rack.has_tile(rack_tile(tile)) {
+            return Err(PlacementError::NotInRack(self.rules.tile_set.name(rack_tile(tile))));
         }
-        
-        let tile: char = tile.to_ascii_uppercase();
+        if tile.is_lowercase() {
+            self.put_blank(tile, row, col)?;
+        } else {
+            self.put_tile(tile, row, col)?;
+        }
+        rack.use_tile(rack_tile(tile));
+        Ok(())
+    }
+
+    pub fn put_tile(&mut self, tile: char, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.check_empty(row, col)?;
+
+        let tile: char = letter_of(tile);
         self.staged_spaces.push((row, col));
 
         self.board[row][col].set_char(tile);
+        Ok(())
+    }
+
+    // stage a blank standing in for letter.
+    pub fn put_blank(&mut self, letter: char, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.put_tile(letter, row, col)?;
+        self.board[row][col].blank = true;
+        Ok(())
+    }
+
+    fn check_empty(&self, row: usize, col: usize) -> Result<(), PlacementError> {
+        if row >= self.size() || col >= self.size() {
+            return Err(PlacementError::OutOfBounds(row, col));
+        }
+        if self.board[row][col].tile != '-' {
+            return Err(PlacementError::Occupied(row, col));
+        }
+        Ok(())
+    }
+
+    pub fn is_blank(&self, row: usize, col: usize) -> bool {
+        self.board[row][col].blank
     }
 
     pub fn remove_tile(&mut self, row: usize, col: usize) {
@@ -297,17 +483,47 @@ impl Board {
         while i < self.staged_spaces.len() {
             if self.staged_spaces[i] == (row, col) {
                 self.staged_spaces.remove(i);
-                self.board[row][col].tile = '-';
+                self.board[row][col].clear();
                 break;
             }
             i += 1;
         }
     }
 
+    // every committed tile as it would sit on a rack, with '*' for blanks.
+    pub fn played_tiles(&self) -> Vec<char> {
+        let mut tiles = Vec::new();
+        for (row, spaces) in self.board.iter().enumerate() {
+            for (col, space) in spaces.iter().enumerate() {
+                if space.tile != '-' && !self.staged_spaces.contains(&(row, col)) {
+                    tiles.push(if space.blank { '*' } else { space.tile });
+                }
+            }
+        }
+        tiles
+    }
+
     pub fn get_tile(&self, row: usize, col: usize) -> char {
         self.board[row][col].tile
     }
 
+    // the points a committed tile is worth. Staged and empty spaces are worth 0.
+    pub fn get_val(&self, row: usize, col: usize) -> i32 {
+        self.board[row][col].val
+    }
+
+    pub fn letter_mult(&self, row: usize, col: usize) -> i32 {
+        self.board[row][col].premium.letter_mult()
+    }
+
+    pub fn word_mult(&self, row: usize, col: usize) -> i32 {
+        self.board[row][col].premium.word_mult()
+    }
+
+    pub fn size(&self) -> usize {
+        self.board.len()
+    }
+
     pub fn get_word_down(&self, row: usize, col: usize) -> Option<String> {
         // given a space in board, return the up/down word that it belongs to, if one exists.
         if self.board[row][col].tile == '-' {
@@ -315,14 +531,8 @@ impl Board {
         }
 
         // get the bounds of the word
-        let start = match self.get_upmost_row(row, col) {
-            Some(n) => n,
-            None => return None,
-        };
-        let end = match self.get_downmost_row(row, col) {
-            Some(n) => n,
-            None => return None,
-        };
+        let start = self.get_upmost_row(row, col)?;
+        let end = self.get_downmost_row(row, col)?;
 
         // one letter words are not allowed
         if start == end {
@@ -344,14 +554,8 @@ impl Board {
         }
 
         // get the bounds of the word
-        let start = match self.get_leftmost_col(row, col) {
-            Some(n) => n,
-            None => return None,
-        };
-        let end = match self.get_rightmost_col(row, col) {
-            Some(n) => n,
-            None => return None,
-        };
+        let start = self.get_leftmost_col(row, col)?;
+        let end = self.get_rightmost_col(row, col)?;
 
         // one letter words are not allowed
         if start == end {
@@ -367,10 +571,7 @@ impl Board {
     }
 
     pub fn word_in_dict(&self, word: String) -> bool {
-        match self.word_list.binary_search(&word) {
-            Ok(_pos) => true,
-            Err(_e) => false,
-        }
+        self.lexicon.is_word(&word.to_uppercase())
     }
 
     pub fn is_word_across(&self, row: usize, col: usize) -> bool {
@@ -406,7 +607,7 @@ impl Board {
         }
 
         let mut end = col;
-        while end < 14 && self.board[row][end+1].tile != '-' {
+        while end + 1 < self.size() && self.board[row][end+1].tile != '-' {
             end += 1;
         }
 
@@ -433,7 +634,7 @@ impl Board {
         }
         
         let mut end = row;
-        while end < 14 && self.board[end+1][col].tile != '-' {
+        while end + 1 < self.size() && self.board[end+1][col].tile != '-' {
             end += 1;
         }
 
@@ -441,6 +642,10 @@ impl Board {
     }
 
     pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    pub fn validate(&self) -> Result<(), PlacementError> {
         // submission are valid if and only if:
         // 1. {
         // There is only one staged tile OR
@@ -451,33 +656,25 @@ impl Board {
         // 4. { All crossings between staged tiles and committed tiles form legal words } AND
 
 
+        if self.staged_spaces.is_empty() {
+            return Err(PlacementError::NoTilesStaged);
+        }
+
         // special case if only one tile is submitted
         if self.staged_spaces.len() == 1 {
             let space = self.staged_spaces[0];
             if !self.neighbors.contains(&space) {
-                println!("({}, {}) does not abut the existing tiles.", space.0, space.1);
-                return false;
+                return Err(PlacementError::NotConnected);
             }
 
-            let word = self.get_word_across(space.0, space.1);
-            if !word.is_none() {
-                let word = word.unwrap();
-                match self.word_list.binary_search(&word) {
-                    Ok(_pos) => println!("{word} accepted"),
-                    Err(_e) => {println!("{word} not found in dictionary"); return false },
-                }
+            if let Some(word) = self.get_word_across(space.0, space.1) {
+                self.check_word(word)?;
             }
-            
-            let word = self.get_word_down(space.0, space.1);
-            if !word.is_none() {
-                let word = word.unwrap();
-                match self.word_list.binary_search(&word) {
-                    Ok(_pos) => println!("{word} accepted"),
-                    Err(_e) => {println!("{word} not found in dictionary"); return false },
-                }
+            if let Some(word) = self.get_word_down(space.0, space.1) {
+                self.check_word(word)?;
             }
 
-            return true;
+            return Ok(());
         }
 
 
@@ -490,8 +687,7 @@ impl Board {
             }
         }
         if !staged_spaces_abut {
-            println!("No staged space abuts the neighbors list.");
-            return false;
+            return Err(PlacementError::NotConnected);
         }
 
         
@@ -544,14 +740,12 @@ impl Board {
             tile1_fixed = tile1_col;
         }
         else {
-            println!("Invalid tile submission.");
-            return false;
+            return Err(PlacementError::NoFixedLine);
         }
 
         for space in &self.staged_spaces {
             if get_fixed_dim(space) != tile1_fixed {
-                println!("Invalid tile submission. No fixed dimension.");
-                return false;
+                return Err(PlacementError::NoFixedLine);
             }
         }
 
@@ -563,7 +757,7 @@ impl Board {
 
 
         // Get the max and min in the free dimension.
-        let mut min_free = 16;
+        let mut min_free = self.size();
         let mut max_free = 0;
         for space in &self.staged_spaces {
             let free_dim = get_free_dim(space);
@@ -581,45 +775,152 @@ impl Board {
         for space in &self.staged_spaces {
             let free_dim = get_free_dim(space);
             if free_dim > lastmost || free_dim < firstmost {
-                println!("Non-contiguous submission.");
-                return false;
+                return Err(PlacementError::NotContiguous);
             }
         }
 
         // check the validity of the main word
         let main_word = get_main_word(self, tile1_row, tile1_col).unwrap();
-        match self.word_list.binary_search(&main_word) {
-            Ok(_pos) => println!("{main_word} accepted"),
-            Err(_e) => {println!("{main_word} not found in dictionary"); return false },
-        }
+        self.check_word(main_word)?;
 
         // check crossing word validities
         for space in &self.staged_spaces {
-            let word = get_crossing_word(self, space.0, space.1);
-            if word.is_none() {
-                continue;
-            }
-            let word = word.unwrap();
-            match self.word_list.binary_search(&word) {
-                Ok(_pos) => println!("{word} accepted"),
-                Err(_e) => {println!("{word} not found in dictionary"); return false },
+            if let Some(word) = get_crossing_word(self, space.0, space.1) {
+                self.check_word(word)?;
             }
         }
-        true
+        Ok(())
     }
 
-    pub fn submit(&mut self) -> i32 {
-        if !self.is_valid() {
-            println!("Not accepting submission");
-            return 0;
+    // Every word the staged tiles form, main word and cross-words alike, each
+    // once and whether or not it is in the word list. Lets a play be
+    // challenged word by word, where validate stops at the first phony.
+    pub fn staged_words(&self) -> Vec<String> {
+        let mut starts = Vec::new();
+        let mut words = Vec::new();
+        for &(row, col) in &self.staged_spaces {
+            if let (Some(word), Some(start)) = (self.get_word_across(row, col), self.get_leftmost_col(row, col))
+                && !starts.contains(&(row, start, true))
+            {
+                starts.push((row, start, true));
+                words.push(word);
+            }
+            if let (Some(word), Some(start)) = (self.get_word_down(row, col), self.get_upmost_row(row, col))
+                && !starts.contains(&(start, col, false))
+            {
+                starts.push((start, col, false));
+                words.push(word);
+            }
+        }
+        words
+    }
+
+    fn check_word(&self, word: String) -> Result<(), PlacementError> {
+        if self.lexicon.is_word(&word) {
+            Ok(())
+        } else {
+            Err(PlacementError::NotAWord(self.rules.tile_set.decode(&word)))
         }
+    }
+
+    // Commits the staged tiles and returns what they scored. On error the
+    // tiles stay staged.
+    pub fn submit(&mut self) -> Result<i32, PlacementError> {
+        self.validate()?;
 
         // score staged word
         let score = self.score();
-        println!("Play is worth {} points.", score);
 
+        self.last_play = self.staged_placement();
+        self.commit_staged();
+
+        Ok(score)
+    }
+
+    // the play most recently accepted by submit, if it has not been taken yet.
+    pub fn take_last_play(&mut self) -> Option<Placement> {
+        self.last_play.take()
+    }
+
+    // Puts tiles that were already played back on the board without checking
+    // or scoring them, e.g. when restoring a saved game. Lowercase tiles are
+    // blanks.
+    pub fn restore_tiles(&mut self, tiles: &[(usize, usize, char)]) -> Result<(), PlacementError> {
+        self.unstage();
+        for &(row, col, tile) in tiles {
+            let placed = if tile.is_lowercase() {
+                self.put_blank(tile, row, col)
+            } else {
+                self.put_tile(tile, row, col)
+            };
+            if let Err(e) = placed {
+                self.unstage();
+                return Err(e);
+            }
+        }
+        self.commit_staged();
+        Ok(())
+    }
+
+    // Takes played tiles off the board again, e.g. to undo a play. The squares
+    // around the remaining tiles become the places to play next to.
+    pub fn take_back(&mut self, tiles: &[(usize, usize, char)]) {
+        self.unstage();
+        for &(row, col, _) in tiles {
+            self.board[row][col].clear();
+            self.board[row][col].val = 0;
+        }
+        self.last_play = None;
+
+        self.neighbors.clear();
+        for row in 0..self.size() {
+            for col in 0..self.size() {
+                if self.board[row][col].tile != '-' {
+                    let space_neighbors = self.get_neighbor_candidates(row, col);
+                    self.neighbors.extend(space_neighbors);
+                }
+            }
+        }
+        if self.neighbors.is_empty() {
+            self.neighbors.insert(self.layout().start);
+        }
+    }
+
+    // the play the staged tiles make, if any.
+    pub fn staged_placement(&self) -> Option<Placement> {
+        let &(row, col) = self.staged_spaces.first()?;
+
+        let across = if self.staged_spaces.len() == 1 {
+            self.get_word_across(row, col).is_some()
+        } else {
+            self.staged_spaces[1].0 == row
+        };
+
+        let mut word = String::new();
+        let (start_row, start_col) = if across {
+            let start = self.get_leftmost_col(row, col)?;
+            for curr_col in start..=self.get_rightmost_col(row, col)? {
+                word.push(self.board[row][curr_col].letter());
+            }
+            (row, start)
+        } else {
+            let start = self.get_upmost_row(row, col)?;
+            for curr_row in start..=self.get_downmost_row(row, col)? {
+                word.push(self.board[curr_row][col].letter());
+            }
+            (start, col)
+        };
+
+        let tiles = self.staged_spaces.iter()
+            .map(|&(row, col)| (row, col, self.board[row][col].letter()))
+            .collect();
+
+        Some(Placement { word, row: start_row, col: start_col, across, tiles })
+    }
+
+    fn commit_staged(&mut self) {
         for space in &self.staged_spaces {
-            self.board[space.0][space.1].val = score_letter(self.board[space.0][space.1].tile);
+            self.board[space.0][space.1].val = self.board[space.0][space.1].points(&self.rules);
 
             // remove space from the neighbor list and add its neighbors provided they 
             // are not already occupied.
@@ -631,102 +932,24 @@ impl Board {
             }
         }
         self.staged_spaces.clear();
-
-        score
     }
 
+    // the empty squares next to (row, col).
     fn get_neighbor_candidates(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
         let mut result = Vec::new();
-
-        let top_edge = row == 0;
-        let bottom_edge = row == 14;
-        let left_edge = col == 0;
-        let right_edge = col == 14;
-
-        // There's probably a better way to do this, but I just went with this.
-        if left_edge && top_edge { // top-left corner
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-            if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
-        } else if left_edge && bottom_edge { // bottom-left corner
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-                if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
-        } else if left_edge { // non-corner left
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-                if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-        } else if bottom_edge && right_edge { // bottom-right corner
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-        } else if bottom_edge { // non-corner bottom
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-            if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
-        } else if right_edge && top_edge { // top-right corner
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-        } else if right_edge { // non-corner right
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-        } else if top_edge { // non-corner top
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-            if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
-        } else { // non-edge case
-            if self.board[row-1][col].tile == '-' {
-                result.push((row-1, col));
-            }
-            if self.board[row+1][col].tile == '-' {
-                result.push((row+1, col));
-            }
-            if self.board[row][col-1].tile == '-' {
-                result.push((row, col-1));
-            }
-            if self.board[row][col+1].tile == '-' {
-                result.push((row, col+1));
-            }
+        if row > 0 {
+            result.push((row - 1, col));
         }
-
+        if row + 1 < self.size() {
+            result.push((row + 1, col));
+        }
+        if col > 0 {
+            result.push((row, col - 1));
+        }
+        if col + 1 < self.size() {
+            result.push((row, col + 1));
+        }
+        result.retain(|&(row, col)| self.board[row][col].tile == '-');
         result
     }
 
@@ -749,18 +972,16 @@ impl Board {
 
         for space in &self.staged_spaces {
             count += 1;
-            word_mult *= self.board[space.0][space.1].word_mult;
+            word_mult *= self.board[space.0][space.1].premium.word_mult();
 
-            let tile_val = score_letter(self.board[space.0][space.1].tile);
-            score += self.board[space.0][space.1].letter_mult * tile_val;
+            let tile_val = self.board[space.0][space.1].points(&self.rules);
+            score += self.board[space.0][space.1].premium.letter_mult() * tile_val;
 
-            let cross_score;
-            if across {
-                cross_score = self.score_down(space.0, space.1);
-            }
-            else {
-                cross_score = self.score_across(space.0, space.1);
-            }
+            let cross_score = if across {
+                self.score_down(space.0, space.1)
+            } else {
+                self.score_across(space.0, space.1)
+            };
 
             cross_score_sum += cross_score;
         }
@@ -787,8 +1008,8 @@ impl Board {
         score *= word_mult;
         score += cross_score_sum;
 
-        if count >= 7 {
-            score += 50;
+        if count >= RACK_SIZE {
+            score += self.rules.bingo_bonus;
         }
 
         score
@@ -804,12 +1025,12 @@ impl Board {
 
         let mut score = 0;
         for curr_col in start_col..=end_col {
-            score += score_letter(self.board[row][curr_col].tile);
+            score += self.board[row][curr_col].points(&self.rules);
         }
 
-        let word_mult = self.board[row][col].word_mult;
-        let letter_mult = self.board[row][col].letter_mult;
-        let crosser_score = score_letter(self.board[row][col].tile);
+        let word_mult = self.board[row][col].premium.word_mult();
+        let letter_mult = self.board[row][col].premium.letter_mult();
+        let crosser_score = self.board[row][col].points(&self.rules);
 
         score -= crosser_score;
         score += crosser_score * letter_mult;
@@ -828,12 +1049,12 @@ impl Board {
 
         let mut score = 0;
         for curr_row in start_row..=end_row {
-            score += score_letter(self.board[curr_row][col].tile);
+            score += self.board[curr_row][col].points(&self.rules);
         }
 
-        let word_mult = self.board[row][col].word_mult;
-        let letter_mult = self.board[row][col].letter_mult;
-        let crosser_score = score_letter(self.board[row][col].tile);
+        let word_mult = self.board[row][col].premium.word_mult();
+        let letter_mult = self.board[row][col].premium.letter_mult();
+        let crosser_score = self.board[row][col].points(&self.rules);
 
         score -= crosser_score;
         score += crosser_score * letter_mult;
@@ -843,7 +1064,7 @@ impl Board {
     }
 
     pub fn get_staged_bounds(&self, across: bool) -> (usize, usize) {
-        let mut min = 15;
+        let mut min = self.size();
         let mut max = 0;
         if across {
             for space in &self.staged_spaces {
@@ -870,174 +1091,115 @@ impl Board {
 
     pub fn unstage_to_rack(&mut self, rack: &mut Rack) {
         for space in &self.staged_spaces {
-            rack.add_tile(self.board[space.0][space.1].tile);
-            self.board[space.0][space.1].tile = '-';
+            if self.board[space.0][space.1].blank {
+                rack.add_tile('*');
+            } else {
+                rack.add_tile(self.board[space.0][space.1].tile);
+            }
+            self.board[space.0][space.1].clear();
         }
         self.staged_spaces.clear();        
     }
 
     pub fn unstage(&mut self) {
         for space in &self.staged_spaces {
-            self.board[space.0][space.1].tile = '-';
+            self.board[space.0][space.1].clear();
         }
         self.staged_spaces.clear();
     }
 
     pub fn show(&self) {
-        let mut result = String::new();
-        let mut  count = 0;
-        write!(&mut result, "   00 01 02 03 04 05 06 07 08 09 10 11 12 13 14\n").unwrap();
-        for row in &self.board {
-            write!(&mut result, "{:02} ", count).unwrap();
-            count += 1;
-            for space in row {
-                if space.tile != '-' {
-                    write!(&mut result, "{}  ", space.tile).unwrap();
-                }
-                else if space.letter_mult == 2 {
-                    write!(&mut result, "dl ").unwrap();
-                }
-                else if space.letter_mult == 3 {
-                    write!(&mut result, "tl ").unwrap();
+        // labelled the way squares are written, see format_square
+        let mut result = String::from("   ");
+        for col in 0..self.size() {
+            write!(&mut result, "{:<3}", column_name(col)).unwrap();
+        }
+        result.push('\n');
+        for (count, row) in self.board.iter().enumerate() {
+            write!(&mut result, "{:>2} ", count + 1).unwrap();
+            for (col, space) in row.iter().enumerate() {
+                if self.staged_spaces.contains(&(count, col)) {
+                    write!(&mut result, "+  ").unwrap();
                 }
-                else if space.word_mult == 2 {
-                    write!(&mut result, "dw ").unwrap();
-                }
-                else if space.word_mult == 3 {
-                    write!(&mut result, "tw ").unwrap();
+                else if space.tile != '-' {
+                    write!(&mut result, "{:<3}", self.rules.tile_set.name(space.letter())).unwrap();
                 }
                 else {
-                    write!(&mut result, "-- ").unwrap();
+                    write!(&mut result, "{} ", space.premium.code()).unwrap();
                 }
             }
             result.push('\n');
         }
 
-        for c in &self.staged_spaces {
-            let row = c.0 + 1;
-            let col = 3*c.1 + 3;
-
-            unsafe { // TODO: Make this safe later
-                result.as_mut_vec()[row*49 + col] = b'+';
-            }
-        }
-
         println!("{result}");
     }
 
-    pub fn write_across(&mut self, word: String, row: usize, col: usize) {
-        if row > 14 || col > 14 { // usize means no need for a lower bounds check
-            println!("Cannot write to ({row}, {col}). Cout of bounds.");
-            return;
-        }
+    pub fn write_across(&mut self, word: String, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.write_word(None, &word, row, col, true)
+    }
 
-        let mut curr_col = col;
-        for c in word.chars() {
-            if curr_col > 14 {
-                println!("Cannot write {word} to ({row}, {col}). Out of bounds.");
-                return;
-            }
-            if self.board[row][curr_col].tile != '-' {
-                if self.board[row][curr_col].tile != c.to_ascii_uppercase() {
-                    println!("Cannot write {word} to ({row}, {col})");
-                    return;
-                }
-            }
-            else {
-                self.put_tile(c, row, curr_col);
-            }
-            curr_col += 1;
-        }
+    pub fn write_down(&mut self, word: String, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.write_word(None, &word, row, col, false)
     }
 
-    pub fn write_down(&mut self, word: String, row: usize, col: usize) {
-        if row > 14 || col > 14 {
-            println!("Cannot write to ({row}, {col}). Out of bounds.");
-            return;
-        }
-        
-        let mut curr_row = row;
-        for c in word.chars() {
-            if curr_row > 14 {
-                println!("Cannot write {word} to ({row}, {col}. Out of bounds.");
-                return;
-            }
-            if self.board[curr_row][col].tile != '-' {
-                if self.board[curr_row][col].tile != c.to_ascii_uppercase() {
-                    println!("Cannot write ({word} {col})");
-                }
-            }
-            else {
-                self.put_tile(c, curr_row, col);
-            }
-            curr_row += 1;
-        }
+    // Lowercase letters in word are played with blanks.
+    pub fn write_across_from_rack(&mut self, rack: &mut Rack, word: String, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.write_word(Some(rack), &word, row, col, true)
     }
-    pub fn write_across_from_rack(&mut self, rack: &mut Rack, word: String, row: usize, col: usize) {
-        if row > 14 || col > 14 { // usize means no need for a lower bounds check
-            println!("Cannot write to ({row}, {col}). Cout of bounds.");
-            return;
-        }
 
-        let mut curr_col = col;
-        for c in word.to_ascii_uppercase().chars() {
-            if curr_col > 14 {
-                println!("Cannot write {word} to ({row}, {col}). Out of bounds.");
-                return;
-            }
-            if self.board[row][curr_col].tile != '-' {
-                if self.board[row][curr_col].tile != c.to_ascii_uppercase() {
-                    println!("Cannot write {word} to ({row}, {col})");
-                    return;
-                }
-            }
-            else if !rack.has_tile(c) {
-                println!("Cannot write {word}. Tile {c} not in rack.");
-                return;
+    pub fn write_down_from_rack(&mut self, rack: &mut Rack, word: String, row: usize, col: usize) -> Result<(), PlacementError> {
+        self.write_word(Some(rack), &word, row, col, false)
+    }
+
+    // Stages the letters of word that are not already on the board. Without a
+    // rack every letter is a normal tile. Nothing is staged if the word does
+    // not fit or the rack is missing a tile.
+    fn write_word(&mut self, rack: Option<&mut Rack>, word: &str, row: usize, col: usize, across: bool) -> Result<(), PlacementError> {
+        let mut new_tiles = Vec::new();
+
+        for (i, c) in word.chars().enumerate() {
+            let (curr_row, curr_col) = if across { (row, col + i) } else { (row + i, col) };
+            if curr_row >= self.size() || curr_col >= self.size() {
+                return Err(PlacementError::OutOfBounds(curr_row, curr_col));
             }
-            else {
-                self.put_tile(c, row, curr_col);
-                rack.use_tile(c);
+
+            let on_board = self.board[curr_row][curr_col].tile;
+            if on_board == '-' {
+                let tile = if rack.is_some() { c } else { letter_of(c) };
+                new_tiles.push((curr_row, curr_col, tile));
+            } else if on_board != letter_of(c) {
+                return Err(PlacementError::Occupied(curr_row, curr_col));
             }
-            curr_col += 1;
         }
-    }
 
-    pub fn write_down_from_rack(&mut self, rack: &mut Rack, word: String, row: usize, col: usize) {
-        if row > 14 || col > 14 {
-            println!("Cannot write to ({row}, {col}). Out of bounds.");
-            return;
-        }
-        
-        let mut curr_row = row;
-        for c in word.to_ascii_uppercase().chars() {
-            if curr_row > 14 {
-                println!("Cannot write {word} to ({row}, {col}. Out of bounds.");
-                return;
-            }
-            if self.board[curr_row][col].tile != '-' {
-                if self.board[curr_row][col].tile != c.to_ascii_uppercase() {
-                    println!("Cannot write ({word} {col})");
+        match rack {
+            Some(rack) => {
+                for &(_, _, tile) in &new_tiles {
+                    let needed = new_tiles.iter().filter(|&&(_, _, other)| rack_tile(other) == rack_tile(tile)).count();
+                    if rack.count(rack_tile(tile)) < needed {
+                        return Err(PlacementError::NotInRack(self.rules.tile_set.name(rack_tile(tile))));
+                    }
+                }
+                for (curr_row, curr_col, tile) in new_tiles {
+                    self.put_tile_from_rack(rack, tile, curr_row, curr_col)?;
                 }
             }
-            else if !rack.has_tile(c) {
-                println!("Cannot write {word}. Tile {c} not in rack.");
-                return;
-            }
-            else {
-                self.put_tile(c, curr_row, col);
-                rack.use_tile(c);
+            None => {
+                for (curr_row, curr_col, tile) in new_tiles {
+                    self.put_tile(tile, curr_row, curr_col)?;
+                }
             }
-            curr_row += 1;
         }
+        Ok(())
     }
 }
 
 #[derive(Clone)]
 pub struct Space {
     tile: char,
-    letter_mult: i32,
-    word_mult: i32,
+    // the tile is a blank standing in for the letter in tile.
+    blank: bool,
+    premium: Premium,
     val: i32,
 }
 
@@ -1045,21 +1207,87 @@ impl Space {
     pub fn set_char(&mut self, tile: char) {
         self.tile = tile;
     }
+
+    // the tile on this space, in lowercase if it is a blank.
+    pub fn letter(&self) -> char {
+        if self.blank {
+            blank_for(self.tile)
+        } else {
+            self.tile
+        }
+    }
+
+    // what the tile on this space is worth before multipliers.
+    pub fn points(&self, rules: &Rules) -> i32 {
+        if self.blank || self.tile == '-' {
+            return 0;
+        }
+        rules.tile_set.score_letter(self.tile)
+    }
+
+    fn clear(&mut self) {
+        self.tile = '-';
+        self.blank = false;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_squares() {
+        assert_eq!(parse_square("8H", 15), Ok((7, 7, true)));
+        assert_eq!(parse_square("h8", 15), Ok((7, 7, false)));
+        assert_eq!(parse_square("15A", 15), Ok((14, 0, true)));
+        assert_eq!(format_square(14, 0, true), "15A");
+        assert_eq!(format_square(2, 4, false), "E3");
+
+        assert!(parse_square("16A", 15).is_err());
+        assert!(parse_square("8P", 15).is_err());
+        assert!(parse_square("1H1", 15).is_err());
+        assert!(parse_square("HH8", 15).is_err());
+        assert!(parse_square("8", 15).is_err());
+        assert!(parse_square("H", 15).is_err());
+        assert!(parse_square("8H!", 15).is_err());
+
+        // boards wider than the alphabet go on to AA, AB...
+        assert_eq!(format_square(0, 25, true), "1Z");
+        assert_eq!(format_square(0, 26, true), "1AA");
+        assert_eq!(format_square(29, 27, false), "AB30");
+        assert_eq!(parse_square("AB30", 30), Ok((29, 27, false)));
+        assert_eq!(parse_square("30ab", 30), Ok((29, 27, true)));
+        assert_eq!(column_name(26 * 27), "AAA");
+        assert!(parse_square("1AE", 30).is_err());
+    }
+
+    #[test]
+    fn test_seeded_bag() {
+        let mut first = Bag::with_seed(42);
+        let mut second = Bag::with_seed(42);
+        assert_eq!(first.tiles, second.tiles);
+        assert_ne!(first.tiles, Bag::with_seed(43).tiles);
+
+        // exchanges reshuffle the same way too
+        let mut racks = [Rack::new(), Rack::new()];
+        for (rack, bag) in racks.iter_mut().zip([&mut first, &mut second]) {
+            rack.draw(bag);
+            let tiles = rack.get_tiles_vec();
+            assert!(rack.swap(bag, tiles[..3].to_vec()));
+        }
+        assert_eq!(racks[0].get_tiles_vec(), racks[1].get_tiles_vec());
+        assert_eq!(first.tiles, second.tiles);
+    }
+
     #[test]
     fn test_hello() {
-        let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
+        let mut board = Board::new("dict.txt".to_string());
 
-        board.put_tile('H', 7, 7);
-        board.put_tile('E', 7, 8);
-        board.put_tile('L', 7, 9);
-        board.put_tile('L', 7, 10);
-        board.put_tile('O', 7, 11);
+        board.put_tile('H', 7, 7).unwrap();
+        board.put_tile('E', 7, 8).unwrap();
+        board.put_tile('L', 7, 9).unwrap();
+        board.put_tile('L', 7, 10).unwrap();
+        board.put_tile('O', 7, 11).unwrap();
 
         board.show();
     }
@@ -1069,82 +1297,81 @@ mod tests {
         // https://www.cross-tables.com/annotated.php?u=54918#3#
 
         let mut score;
-        let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
+        let mut board = Board::new("dict.txt".to_string());
 
-        board.put_tile('J', 6, 7);
-        board.put_tile('U', 7, 7);
-        board.put_tile('B', 8, 7);
-        board.put_tile('E', 9, 7);
+        board.put_tile('J', 6, 7).unwrap();
+        board.put_tile('U', 7, 7).unwrap();
+        board.put_tile('B', 8, 7).unwrap();
+        board.put_tile('E', 9, 7).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 26);
 
-        board.put_tile('O', 8, 8);
-        board.put_tile('X', 9, 8);
+        board.put_tile('O', 8, 8).unwrap();
+        board.put_tile('X', 9, 8).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 24);
 
-        board.put_tile('T', 5, 8);
-        board.put_tile('O', 6, 8);
+        board.put_tile('T', 5, 8).unwrap();
+        board.put_tile('O', 6, 8).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 13);
 
-        board.put_tile('B', 3, 9);
-        board.put_tile('R', 4, 9);
-        board.put_tile('O', 5, 9);
-        board.put_tile('W', 6, 9);
+        board.put_tile('B', 3, 9).unwrap();
+        board.put_tile('R', 4, 9).unwrap();
+        board.put_tile('O', 5, 9).unwrap();
+        board.put_tile('W', 6, 9).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 28);
 
-        board.put_tile('H', 2, 10);
-        board.put_tile('A', 3, 10);
-        board.put_tile('E', 4, 10);
-        board.put_tile('T', 5, 10);
+        board.put_tile('H', 2, 10).unwrap();
+        board.put_tile('A', 3, 10).unwrap();
+        board.put_tile('E', 4, 10).unwrap();
+        board.put_tile('T', 5, 10).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 25);
 
-        board.put_tile('S', 7, 9);
-        board.put_tile('A', 7, 10);
-        board.put_tile('D', 7, 11);
-        board.put_tile('D', 7, 12);
-        board.put_tile('E', 7, 13);
-        board.put_tile('N', 7, 14);
+        board.put_tile('S', 7, 9).unwrap();
+        board.put_tile('A', 7, 10).unwrap();
+        board.put_tile('D', 7, 11).unwrap();
+        board.put_tile('D', 7, 12).unwrap();
+        board.put_tile('E', 7, 13).unwrap();
+        board.put_tile('N', 7, 14).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 40);
 
-        board.put_tile('T', 1, 11);
-        board.put_tile('O', 2, 11);
-        board.put_tile('G', 3, 11);
+        board.put_tile('T', 1, 11).unwrap();
+        board.put_tile('O', 2, 11).unwrap();
+        board.put_tile('G', 3, 11).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 25);
 
-        board.put_tile('L', 10, 1);
-        board.put_tile('E', 10, 2);
-        board.put_tile('F', 10, 3);
-        board.put_tile('T', 10, 4);
-        board.put_tile('I', 10, 5);
-        board.put_tile('E', 10, 6);
-        board.put_tile('S', 10, 7);
+        board.put_tile('L', 10, 1).unwrap();
+        board.put_tile('E', 10, 2).unwrap();
+        board.put_tile('F', 10, 3).unwrap();
+        board.put_tile('T', 10, 4).unwrap();
+        board.put_tile('I', 10, 5).unwrap();
+        board.put_tile('E', 10, 6).unwrap();
+        board.put_tile('S', 10, 7).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 84);
 
-        board.put_tile('I', 0, 13);
-        board.put_tile('N', 1, 13);
-        board.put_tile('T', 2, 13);
-        board.put_tile('E', 3, 13);
-        board.put_tile('R', 4, 13);
-        board.put_tile('L', 5, 13);
-        board.put_tile('I', 6, 13);
+        board.put_tile('I', 0, 13).unwrap();
+        board.put_tile('N', 1, 13).unwrap();
+        board.put_tile('T', 2, 13).unwrap();
+        board.put_tile('E', 3, 13).unwrap();
+        board.put_tile('R', 4, 13).unwrap();
+        board.put_tile('L', 5, 13).unwrap();
+        board.put_tile('I', 6, 13).unwrap();
 
-        score = board.submit();
-        assert_eq!(score, 0); // invalid word
+        assert!(matches!(board.submit(), Err(PlacementError::NotAWord(_)))); // invalid word
     }
 
     #[test]
@@ -1152,205 +1379,205 @@ mod tests {
         // https://www.cross-tables.com/annotated.php?u=54917#3#
 
         let mut score;
-        let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
+        let mut board = Board::new("dict.txt".to_string());
 
-        board.put_tile('H', 6, 7);
-        board.put_tile('I', 7, 7);
-        board.put_tile('D', 8, 7);
+        board.put_tile('H', 6, 7).unwrap();
+        board.put_tile('I', 7, 7).unwrap();
+        board.put_tile('D', 8, 7).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 14);
 
-        board.put_tile('Z', 8, 2);
-        board.put_tile('I', 8, 3);
-        board.put_tile('N', 8, 4);
-        board.put_tile('G', 8, 5);
-        board.put_tile('E', 8, 6);
+        board.put_tile('Z', 8, 2).unwrap();
+        board.put_tile('I', 8, 3).unwrap();
+        board.put_tile('N', 8, 4).unwrap();
+        board.put_tile('G', 8, 5).unwrap();
+        board.put_tile('E', 8, 6).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 28);
 
-        board.put_tile('I', 9, 2);
-        board.put_tile('N', 10, 2);
-        board.put_tile('N', 11, 2);
-        board.put_tile('I', 12, 2);
-        board.put_tile('A', 13, 2);
+        board.put_tile('I', 9, 2).unwrap();
+        board.put_tile('N', 10, 2).unwrap();
+        board.put_tile('N', 11, 2).unwrap();
+        board.put_tile('I', 12, 2).unwrap();
+        board.put_tile('A', 13, 2).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 30);
         
-        board.put_tile('J', 13, 1);
-        board.put_tile('R', 13, 3);
+        board.put_tile('J', 13, 1).unwrap();
+        board.put_tile('R', 13, 3).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 20);
 
-        board.put_tile('N', 10, 4);
-        board.put_tile('E', 11, 4);
-        board.put_tile('B', 12, 4);
-        board.put_tile('S', 13, 4);
+        board.put_tile('N', 10, 4).unwrap();
+        board.put_tile('E', 11, 4).unwrap();
+        board.put_tile('B', 12, 4).unwrap();
+        board.put_tile('S', 13, 4).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 23);
 
-        board.put_tile('W', 11, 0);
-        board.put_tile('I', 11, 1);
-        board.put_tile('T', 11, 3);
-        board.put_tile('R', 11, 5);
+        board.put_tile('W', 11, 0).unwrap();
+        board.put_tile('I', 11, 1).unwrap();
+        board.put_tile('T', 11, 3).unwrap();
+        board.put_tile('R', 11, 5).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 26);
 
-        board.put_tile('V', 4, 8);
-        board.put_tile('U', 5, 8);
-        board.put_tile('M', 6, 8);
+        board.put_tile('V', 4, 8).unwrap();
+        board.put_tile('U', 5, 8).unwrap();
+        board.put_tile('M', 6, 8).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 21);
 
-        board.put_tile('O', 1, 4);
-        board.put_tile('U', 2, 4);
-        board.put_tile('T', 3, 4);
-        board.put_tile('E', 4, 4);
-        board.put_tile('A', 5, 4);
-        board.put_tile('T', 6, 4);
-        board.put_tile('E', 7, 4);
+        board.put_tile('O', 1, 4).unwrap();
+        board.put_tile('U', 2, 4).unwrap();
+        board.put_tile('T', 3, 4).unwrap();
+        board.put_tile('E', 4, 4).unwrap();
+        board.put_tile('A', 5, 4).unwrap();
+        board.put_tile('T', 6, 4).unwrap();
+        board.put_tile('E', 7, 4).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 66);
 
-        board.put_tile('O', 0, 5);
-        board.put_tile('X', 1, 5);
+        board.put_tile('O', 0, 5).unwrap();
+        board.put_tile('X', 1, 5).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 50);
 
-        board.put_tile('O', 10, 5);
-        board.put_tile('N', 10, 6);
-        board.put_tile('D', 10, 7);
-        board.put_tile('A', 10, 8);
-        board.put_tile('I', 10, 9);
-        board.put_tile('R', 10, 10);
-        board.put_tile('Y', 10, 11);
+        board.put_tile('O', 10, 5).unwrap();
+        board.put_tile('N', 10, 6).unwrap();
+        board.put_tile('D', 10, 7).unwrap();
+        board.put_tile('A', 10, 8).unwrap();
+        board.put_tile('I', 10, 9).unwrap();
+        board.put_tile('R', 10, 10).unwrap();
+        board.put_tile('Y', 10, 11).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 76);
 
-        board.put_tile('F', 0, 6);
-        board.put_tile('F', 0, 7);
-        board.put_tile('S', 0, 8);
+        board.put_tile('F', 0, 6).unwrap();
+        board.put_tile('F', 0, 7).unwrap();
+        board.put_tile('S', 0, 8).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 30);
 
-        board.put_tile('E', 11, 7);
-        board.put_tile('C', 12, 7);
-        board.put_tile('A', 13, 7);
-        board.put_tile('L', 14, 7);
+        board.put_tile('E', 11, 7).unwrap();
+        board.put_tile('C', 12, 7).unwrap();
+        board.put_tile('A', 13, 7).unwrap();
+        board.put_tile('L', 14, 7).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 27);
 
-        board.put_tile('M', 9, 11);
-        board.put_tile('A', 9, 10);
-        board.put_tile('H', 9, 9);
+        board.put_tile('M', 9, 11).unwrap();
+        board.put_tile('A', 9, 10).unwrap();
+        board.put_tile('H', 9, 9).unwrap();
         
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 38);
 
-        board.put_tile('S', 14, 2);
-        board.put_tile('O', 14, 1);
-        board.put_tile('C', 14, 0);
+        board.put_tile('S', 14, 2).unwrap();
+        board.put_tile('O', 14, 1).unwrap();
+        board.put_tile('C', 14, 0).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 40);
 
-        board.put_tile('T', 9, 3);
-        board.put_tile('P', 7, 3);
+        board.put_tile('T', 9, 3).unwrap();
+        board.put_tile('P', 7, 3).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 17);
 
-        board.put_tile('I', 14, 10);
-        board.put_tile('E', 14, 14);
-        board.put_tile('A', 14, 12);
-        board.put_tile('G', 14, 11);
-        board.put_tile('V', 14, 9);
-        board.put_tile('T', 14, 13);
-        board.put_tile('E', 14, 8);
+        board.put_tile('I', 14, 10).unwrap();
+        board.put_tile('E', 14, 14).unwrap();
+        board.put_tile('A', 14, 12).unwrap();
+        board.put_tile('G', 14, 11).unwrap();
+        board.put_tile('V', 14, 9).unwrap();
+        board.put_tile('T', 14, 13).unwrap();
+        board.put_tile('E', 14, 8).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 92);
 
-        board.put_tile('A', 13, 13);
-        board.put_tile('Q', 12, 13);
+        board.put_tile('A', 13, 13).unwrap();
+        board.put_tile('Q', 12, 13).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 24);
 
-        board.put_tile('T', 5, 5);
-        board.put_tile('N', 7, 5);
-        board.put_tile('O', 6, 5);
+        board.put_tile('T', 5, 5).unwrap();
+        board.put_tile('N', 7, 5).unwrap();
+        board.put_tile('O', 6, 5).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 18);
 
-        board.put_tile('E', 8, 0);
-        board.put_tile('B', 7, 0);
-        board.put_tile('E', 10, 0);
-        board.put_tile('D', 9, 0);
+        board.put_tile('E', 8, 0).unwrap();
+        board.put_tile('B', 7, 0).unwrap();
+        board.put_tile('E', 10, 0).unwrap();
+        board.put_tile('D', 9, 0).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 33);
 
-        board.put_tile('S', 3, 7);
-        board.put_tile('U', 3, 11);
-        board.put_tile('I', 3, 9);
-        board.put_tile('O', 3, 8);
-        board.put_tile('L', 3, 10);
-        board.put_tile('E', 3, 13);
-        board.put_tile('R', 3, 12);
+        board.put_tile('S', 3, 7).unwrap();
+        board.put_tile('U', 3, 11).unwrap();
+        board.put_tile('I', 3, 9).unwrap();
+        board.put_tile('O', 3, 8).unwrap();
+        board.put_tile('L', 3, 10).unwrap();
+        board.put_tile('E', 3, 13).unwrap();
+        board.put_tile('R', 3, 12).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 75);
 
-        board.put_tile('A', 6, 1);
-        board.put_tile('Y', 7, 1);
-        board.put_tile('P', 5, 1);
+        board.put_tile('A', 6, 1).unwrap();
+        board.put_tile('Y', 7, 1).unwrap();
+        board.put_tile('P', 5, 1).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 21);
 
-        board.put_tile('K', 1, 9);
-        board.put_tile('O', 1, 8);
+        board.put_tile('K', 1, 9).unwrap();
+        board.put_tile('O', 1, 8).unwrap();
         
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 18);
 
-        board.put_tile('W', 0, 14);
-        board.put_tile('G', 2, 14);
-        board.put_tile('S', 3, 14);
-        board.put_tile('A', 1, 14);
+        board.put_tile('W', 0, 14).unwrap();
+        board.put_tile('G', 2, 14).unwrap();
+        board.put_tile('S', 3, 14).unwrap();
+        board.put_tile('A', 1, 14).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 36);
 
-        board.put_tile('O', 2, 2);
-        board.put_tile('D', 1, 2);
-        board.put_tile('R', 4, 2);
-        board.put_tile('U', 3, 2);
-        board.put_tile('R', 6, 2);
-        board.put_tile('E', 5, 2);
+        board.put_tile('O', 2, 2).unwrap();
+        board.put_tile('D', 1, 2).unwrap();
+        board.put_tile('R', 4, 2).unwrap();
+        board.put_tile('U', 3, 2).unwrap();
+        board.put_tile('R', 6, 2).unwrap();
+        board.put_tile('E', 5, 2).unwrap();
 
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 23);
 
-        board.put_tile('I', 7, 12);
-        board.put_tile('T', 6, 12);
-        board.put_tile('E', 9, 12);
-        board.put_tile('L', 8, 12);
+        board.put_tile('I', 7, 12).unwrap();
+        board.put_tile('T', 6, 12).unwrap();
+        board.put_tile('E', 9, 12).unwrap();
+        board.put_tile('L', 8, 12).unwrap();
         
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 15);
     }
 
@@ -1358,41 +1585,163 @@ mod tests {
     fn test_fuzz_3() {
         // https://www.cross-tables.com/annotated.php?u=55086#1#
         let mut score;
-        let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
+        let mut board = Board::new("dict.txt".to_string());
 
-        board.write_across(String::from("leavy"), 7, 7);
-        score = board.submit();
+        board.write_across(String::from("leavy"), 7, 7).unwrap();
+        score = board.submit().unwrap();
         assert_eq!(score, 30);
 
-        board.write_across(String::from("oration"), 8, 2);
-        score = board.submit();
+        board.write_across(String::from("oration"), 8, 2).unwrap();
+        score = board.submit().unwrap();
         assert_eq!(score, 65);
 
-        board.write_down(String::from("fondly"), 2, 11);
+        board.write_down(String::from("fondly"), 2, 11).unwrap();
         board.show();
-        score = board.submit();
+        score = board.submit().unwrap();
         assert_eq!(score, 26);
     }
 
+    #[test]
+    fn test_blank() {
+        let mut board = Board::new("dict.txt".to_string());
+
+        // the blank U scores nothing, even once it has been committed
+        board.put_tile('Q', 7, 7).unwrap();
+        board.put_blank('u', 7, 8).unwrap();
+        board.put_tile('I', 7, 9).unwrap();
+        board.put_tile('T', 7, 10).unwrap();
+        assert_eq!(board.submit().unwrap(), 24);
+        assert!(board.is_blank(7, 8));
+        assert_eq!(board.get_tile(7, 8), 'U');
+
+        board.put_tile('S', 7, 11).unwrap();
+        assert_eq!(board.submit().unwrap(), 14);
+
+        let mut rack = Rack::from_tiles("*O");
+        board.write_down_from_rack(&mut rack, String::from("Qi"), 7, 7).unwrap();
+        assert!(board.is_valid());
+        assert_eq!(board.score(), 10);
+        assert_eq!(rack.get_tiles_vec(), vec!['O']);
+
+        board.unstage_to_rack(&mut rack);
+        assert_eq!(rack.get_tiles_vec(), vec!['O', '*']);
+    }
+
+    #[test]
+    fn test_wwf_rules() {
+        let lexicon = Arc::new(Lexicon::from_words(["RETAINS"]));
+        let mut standard = Board::with_lexicon(lexicon.clone());
+        let mut wwf = Board::with_rules(lexicon, Arc::new(Rules::wwf()));
+
+        // the middle square doubles the word in Scrabble but is plain in WWF,
+        // where N is worth 2 and a bingo 35
+        standard.write_across(String::from("RETAINS"), 7, 4).unwrap();
+        wwf.write_across(String::from("RETAINS"), 7, 4).unwrap();
+        assert_eq!(standard.submit().unwrap(), 64);
+        assert_eq!(wwf.submit().unwrap(), 43);
+
+        assert_eq!(Bag::with_rules(&Rules::wwf(), 1).size(), 104);
+        let rack = Rack::from_tiles("B*");
+        assert_eq!(rack.value(&Rules::wwf()), 4);
+        assert_eq!(rack.value(&Rules::standard()), 3);
+    }
+
+    #[test]
+    fn test_quadruple_squares() {
+        let layout = Layout::parse("start 0 0\nqw ql --\n-- -- --\n-- -- --\n").unwrap();
+        let rules = Rules { layout: Arc::new(layout), ..Rules::standard() };
+        let mut board = Board::with_rules(Arc::new(Lexicon::from_words(["QI"])), Arc::new(rules));
+
+        // (10 + 4 * 1) * 4
+        board.write_across(String::from("QI"), 0, 0).unwrap();
+        assert_eq!(board.submit().unwrap(), 56);
+    }
+
+    #[test]
+    fn test_spanish_rules() {
+        let rules = Rules::spanish();
+        let chico = rules.tile_set.encode("CHICO");
+        let lexicon = Arc::new(Lexicon::from_words([chico.as_str(), rules.tile_set.encode("LLAMA").as_str()]));
+        let mut board = Board::with_rules(lexicon, Arc::new(rules.clone()));
+
+        let mut rack = Rack::from_tiles(&rules.tile_set.encode("CHICOLL"));
+        assert_eq!(rack.len(), 5);
+
+        // CH is one tile worth 5: (5 + 1 + 3 + 1) * 2
+        board.write_across_from_rack(&mut rack, chico, 7, 5).unwrap();
+        assert_eq!(board.submit().unwrap(), 20);
+        assert_eq!(rack.len(), 1);
+
+        // a missing digraph is reported by its name
+        let llama = rules.tile_set.encode("LLAMA");
+        assert_eq!(
+            board.write_down_from_rack(&mut rack, llama.clone(), 8, 5),
+            Err(PlacementError::NotInRack(String::from("A")))
+        );
+        let mut empty = Rack::new();
+        let missing = board.write_down_from_rack(&mut empty, llama, 8, 5).unwrap_err();
+        assert_eq!(missing, PlacementError::NotInRack(String::from("LL")));
+        assert_eq!(missing.to_string(), "Tile LL is not in the rack.");
+
+        // and so are words the dictionary does not have, even with a blank
+        let mut rack = Rack::from_tiles(&rules.tile_set.encode("CHO*"));
+        let word = rules.tile_set.encode("CHOll");
+        board.write_down_from_rack(&mut rack, word, 6, 9).unwrap();
+        let phony = board.submit().unwrap_err();
+        assert_eq!(phony, PlacementError::NotAWord(String::from("CHOLL")));
+        assert_eq!(phony.to_string(), "CHOLL is not in the dictionary.");
+    }
+
+    #[test]
+    fn test_staged_words() {
+        let lexicon = Arc::new(Lexicon::from_words(["QI", "ZA"]));
+        let mut board = Board::with_lexicon(lexicon);
+        board.write_across(String::from("QI"), 7, 7).unwrap();
+        board.submit().unwrap();
+
+        // ZA over QI makes ZQ and AI down as well
+        board.write_across(String::from("ZA"), 6, 7).unwrap();
+        assert_eq!(board.staged_words(), vec!["ZA", "ZQ", "AI"]);
+        assert!(!board.is_valid());
+        board.unstage();
+        assert!(board.staged_words().is_empty());
+    }
+
     #[test]
     fn test_contiguity() {
-        let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
+        let mut board = Board::new("dict.txt".to_string());
 
-        board.write_across(String::from("leave"), 7, 7);
-        assert!(board.submit() > 0);
+        board.write_across(String::from("leave"), 7, 7).unwrap();
+        assert!(board.submit().unwrap() > 0);
 
-        board.write_across(String::from("oration"), 8, 2);
-        assert!(board.submit() > 0);
+        board.write_across(String::from("oration"), 8, 2).unwrap();
+        assert!(board.submit().unwrap() > 0);
 
 
         // try non-contiguous
-        board.write_down(String::from("floo"), 6, 7);
-        board.put_tile('r', 11, 7); // not contiguous
-        assert!(!board.is_valid());
+        board.write_down(String::from("floo"), 6, 7).unwrap();
+        board.put_tile('r', 11, 7).unwrap(); // not contiguous
+        assert_eq!(board.validate(), Err(PlacementError::NotContiguous));
         board.unstage();
 
         // try word separate from the rest
-        board.write_across(String::from("fond"), 0, 0);
-        assert!(!board.is_valid());
+        board.write_across(String::from("fond"), 0, 0).unwrap();
+        assert_eq!(board.validate(), Err(PlacementError::NotConnected));
+        board.unstage();
+
+        // try squares that are taken or off the board
+        assert_eq!(board.put_tile('A', 7, 7), Err(PlacementError::Occupied(7, 7)));
+        assert_eq!(board.write_across(String::from("fond"), 0, 12), Err(PlacementError::OutOfBounds(0, 15)));
+        assert_eq!(PlacementError::OutOfBounds(0, 15).to_string(), "1P is off the board.");
+        assert_eq!(PlacementError::Occupied(7, 10).to_string(), "8K is already taken.");
+        assert_eq!(board.write_across(String::from("lead"), 7, 7), Err(PlacementError::Occupied(7, 10)));
+        assert_eq!(board.validate(), Err(PlacementError::NoTilesStaged));
+
+        let mut rack = Rack::from_tiles("FO");
+        assert_eq!(
+            board.write_down_from_rack(&mut rack, String::from("FOOD"), 0, 0),
+            Err(PlacementError::NotInRack(String::from("O")))
+        );
+        assert_eq!(rack.len(), 2);
     }
 }
@@ -0,0 +1,433 @@
+// Game records in the GCG format shared by most Scrabble software. A record
+// names the players and then lists one line per turn:
+//
+//   #player1 p1 Player 1
+//   #player2 p2 Player 2
+//   >p1: ABDEKLY 8F BLEAK +24 24
+//   >p2: QVVWWXZ -QVVWW +0 0
+//   >p1: ACEHNRT - +0 24
+//   >p2: ADIOSTU H5 DOS. +10 10
+//   >p2: (V) +4 14
+//   >p1: (V) -4 20
+//
+// Squares are written as row number then column letter for plays across and
+// the other way round for plays down. A '.' stands for a tile that was
+// already on the board, a lowercase letter for a blank and '?' for a blank on
+// a rack. A play challenged off the board is followed by a "--" line taking
+// its points back, and points won in a challenge or lost on time have lines
+// of their own:
+//
+//   >p1: AEINRST -- -70 0
+//   >p2: ADIOSTU (challenge) +5 15
+//   >p1: (time) -10 -10
+
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+use crate::game::{blank_for, format_square, letter_of, parse_square, Bag, Board};
+use crate::lexicon::Lexicon;
+use crate::player::Player;
+use crate::rules::Rules;
+use crate::session::{Action, HistoryEntry, Session};
+use crate::shell::RacklessHelper;
+use crate::tileset::TileSet;
+
+pub fn write<W: Write>(session: &Session, writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "#character-encoding UTF-8")?;
+    // players keep the names they were imported with
+    let nickname = |id: i32| {
+        let player = session.players.iter().find(|player| player.id == id);
+        player.and_then(|player| player.nickname.clone()).unwrap_or_else(|| format!("p{id}"))
+    };
+    for player in &session.players {
+        let full_name = player.full_name.clone().unwrap_or_else(|| format!("Player {}", player.id));
+        writeln!(writer, "#player{} {} {full_name}", player.id, nickname(player.id))?;
+    }
+
+    // replay the plays to find out which squares each one played through
+    let tile_set = &session.board.rules().tile_set;
+    let size = session.board.size();
+    let mut occupied = vec![vec![false; size]; size];
+    // the squares the last play filled, which a withdrawn play empties again
+    let mut last_play = Vec::new();
+
+    for entry in &session.history {
+        write!(writer, ">{}: ", nickname(entry.player))?;
+        match &entry.action {
+            Action::Play { word, row, col, across } => {
+                let mut tiles = String::new();
+                last_play.clear();
+                for (i, tile) in word.chars().enumerate() {
+                    let (row, col) = if *across { (*row, col + i) } else { (row + i, *col) };
+                    if occupied[row][col] {
+                        tiles.push('.');
+                    } else {
+                        occupied[row][col] = true;
+                        last_play.push((row, col));
+                        tiles.push_str(&tile_set.name(tile));
+                    }
+                }
+                write!(writer, "{} {} {}", format_rack(&entry.rack, tile_set), format_square(*row, *col, *across), tiles)?;
+            }
+            Action::Withdrawn => {
+                for (row, col) in last_play.drain(..) {
+                    occupied[row][col] = false;
+                }
+                write!(writer, "{} --", format_rack(&entry.rack, tile_set))?;
+            }
+            Action::ChallengeBonus => write!(writer, "{} (challenge)", format_rack(&entry.rack, tile_set))?,
+            Action::TimePenalty => write!(writer, "(time)")?,
+            Action::Exchange(exchanged) => {
+                write!(writer, "{} -{}", format_rack(&entry.rack, tile_set), format_rack(exchanged, tile_set))?;
+            }
+            Action::Pass => write!(writer, "{} -", format_rack(&entry.rack, tile_set))?,
+            Action::EndRack(tiles) => write!(writer, "({})", format_rack(tiles, tile_set))?,
+        }
+        writeln!(writer, " {:+} {}", entry.score, entry.total)?;
+    }
+
+    writer.flush()
+}
+
+// Reads a record and replays it onto a fresh board. Plays are not checked
+// against the lexicon, since the game may have used another word list. The
+// players come back as humans without racks and the bag holds every tile that
+// is not on the board, shuffled from the seed.
+pub fn read<R: BufRead>(reader: &mut R, lexicon: Arc<Lexicon>, rules: Arc<Rules>, seed: u64) -> io::Result<Session> {
+    // each player's nickname and full name
+    let mut names: Vec<(String, String)> = Vec::new();
+    let mut history = Vec::new();
+    let mut board = Board::with_rules(lexicon, rules);
+    let mut last_play = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("#player") {
+            let mut fields = rest.split_whitespace();
+            fields.next(); // the player's number
+            if let Some(nickname) = fields.next()
+                && !names.iter().any(|(known, _)| known == nickname)
+            {
+                let full_name = fields.collect::<Vec<_>>().join(" ");
+                let full_name = if full_name.is_empty() { nickname.to_string() } else { full_name };
+                names.push((nickname.to_string(), full_name));
+            }
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix('>') else {
+            continue; // comments, notes and other pragmas
+        };
+        let Some((nickname, turn)) = rest.split_once(':') else {
+            return Err(invalid_data(&format!("bad turn '{line}'")));
+        };
+        let player = match names.iter().position(|(known, _)| known == nickname) {
+            Some(i) => i,
+            None => {
+                names.push((nickname.to_string(), nickname.to_string()));
+                names.len() - 1
+            }
+        };
+
+        let entry = parse_turn(&mut board, player as i32 + 1, turn, &mut last_play)
+            .map_err(|e| invalid_data(&format!("{e} in '{line}'")))?;
+        history.push(entry);
+    }
+
+    if names.is_empty() {
+        return Err(invalid_data("no players"));
+    }
+
+    let mut players: Vec<Player> = Vec::new();
+    for (id, (nickname, full_name)) in (1..).zip(names) {
+        let mut player = Player::new(id, Box::new(RacklessHelper));
+        player.nickname = Some(nickname);
+        player.full_name = Some(full_name);
+        players.push(player);
+    }
+    for entry in &history {
+        players[entry.player as usize - 1].score = entry.total;
+    }
+
+    // whoever moved last is followed by the next player
+    let turn = history.last().map_or(0, |entry| entry.player as usize % players.len());
+    let scoreless_turns = history
+        .iter()
+        .rev()
+        .filter(|entry| !entry.action.is_adjustment())
+        .take_while(|entry| entry.action.is_scoreless())
+        .count() as u32;
+
+    let mut bag = Bag::with_rules(board.rules(), seed);
+    for tile in board.played_tiles() {
+        if let Some(i) = bag.tiles.iter().position(|&t| t == tile) {
+            bag.tiles.swap_remove(i);
+        }
+    }
+    bag.shuffle();
+
+    Ok(Session { board, bag, players, turn, scoreless_turns, history, undo: Vec::new(), redo: Vec::new() })
+}
+
+// Parses everything after "nickname:" and puts any play on the board, keeping
+// the tiles it placed in last_play. A withdrawn play takes those back off.
+fn parse_turn(
+    board: &mut Board,
+    player: i32,
+    turn: &str,
+    last_play: &mut Vec<(usize, usize, char)>,
+) -> Result<HistoryEntry, String> {
+    // tiles like CH become single chars before anything else is read, but
+    // not in words like "(challenge)" that are not tiles
+    let keyword = |field: &str| matches!(field, "--" | "(challenge)" | "(time)");
+    let tile_set = &board.rules().tile_set;
+    let turn: Vec<String> = turn
+        .split_whitespace()
+        .map(|field| if keyword(field) { field.to_string() } else { tile_set.encode(field) })
+        .collect();
+    let fields: Vec<&str> = turn.iter().map(String::as_str).collect();
+
+    let (rack, action, score, total) = match fields[..] {
+        [rack, "--", score, total] => (parse_rack(rack)?, withdraw(board, last_play)?, score, total),
+        ["--", score, total] => (Vec::new(), withdraw(board, last_play)?, score, total),
+        [rack, "(challenge)", score, total] => (parse_rack(rack)?, Action::ChallengeBonus, score, total),
+        ["(challenge)", score, total] => (Vec::new(), Action::ChallengeBonus, score, total),
+        ["(time)", score, total] => (Vec::new(), Action::TimePenalty, score, total),
+        [tiles, score, total] if tiles.starts_with('(') && tiles.ends_with(')') => {
+            let tiles = parse_rack(&tiles[1..tiles.len() - 1])?;
+            (tiles.clone(), Action::EndRack(tiles), score, total)
+        }
+        [rack, "-", score, total] => (parse_rack(rack)?, Action::Pass, score, total),
+        [rack, exchanged, score, total] if exchanged.starts_with('-') => {
+            let exchanged = parse_rack(&exchanged[1..])?;
+            (parse_rack(rack)?, Action::Exchange(exchanged), score, total)
+        }
+        [rack, square, tiles, score, total] => {
+            let (row, col, across) = parse_square(square, board.size())?;
+            let word = play_tiles(board, row, col, across, tiles, last_play)?;
+            (parse_rack(rack)?, Action::Play { word, row, col, across }, score, total)
+        }
+        // players without a rack leave it out
+        ["-", score, total] => (Vec::new(), Action::Pass, score, total),
+        [square, tiles, score, total] => {
+            let (row, col, across) = parse_square(square, board.size())?;
+            let word = play_tiles(board, row, col, across, tiles, last_play)?;
+            (Vec::new(), Action::Play { word, row, col, across }, score, total)
+        }
+        _ => return Err(String::from("unsupported turn")),
+    };
+
+    Ok(HistoryEntry {
+        player,
+        rack,
+        action,
+        score: score.parse().map_err(|_| format!("bad score '{score}'"))?,
+        total: total.parse().map_err(|_| format!("bad total '{total}'"))?,
+    })
+}
+
+// Takes the tiles of the last play back off the board.
+fn withdraw(board: &mut Board, last_play: &mut Vec<(usize, usize, char)>) -> Result<Action, String> {
+    if last_play.is_empty() {
+        return Err(String::from("no play to withdraw"));
+    }
+    board.take_back(last_play);
+    last_play.clear();
+    Ok(Action::Withdrawn)
+}
+
+// Puts the new tiles of a play on the board and returns the whole word.
+fn play_tiles(
+    board: &mut Board,
+    row: usize,
+    col: usize,
+    across: bool,
+    tiles: &str,
+    last_play: &mut Vec<(usize, usize, char)>,
+) -> Result<String, String> {
+    let mut word = String::new();
+    let mut placed = Vec::new();
+
+    for (i, tile) in tiles.chars().enumerate() {
+        let (row, col) = if across { (row, col + i) } else { (row + i, col) };
+        if row >= board.size() || col >= board.size() {
+            return Err(String::from("play runs off the board"));
+        }
+
+        let on_board = board.get_tile(row, col);
+        if on_board == '-' {
+            if !tile.is_alphabetic() {
+                return Err(format!("'{tile}' is not a tile"));
+            }
+            placed.push((row, col, tile));
+            word.push(tile);
+        } else if tile == '.' || letter_of(tile) == on_board {
+            // some programs write the letters played through instead of '.'
+            word.push(if board.is_blank(row, col) { blank_for(on_board) } else { on_board });
+        } else {
+            return Err(format!("square {} is already taken", format_square(row, col, across)));
+        }
+    }
+
+    board.restore_tiles(&placed).map_err(|e| e.to_string())?;
+    *last_play = placed;
+    Ok(word)
+}
+
+// GCG writes blanks on a rack as '?'.
+fn format_rack(tiles: &[char], tile_set: &TileSet) -> String {
+    tiles.iter().map(|&tile| if tile == '*' { String::from("?") } else { tile_set.name(tile) }).collect()
+}
+
+// s has already been encoded with the tile set.
+fn parse_rack(s: &str) -> Result<Vec<char>, String> {
+    s.chars()
+        .map(|tile| match tile {
+            '?' => Ok('*'),
+            tile if tile.is_alphabetic() => Ok(letter_of(tile)),
+            _ => Err(format!("bad tile '{tile}'")),
+        })
+        .collect()
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::greedy::find_greediest_word;
+    use crate::game::Rack;
+
+    const RECORD: &str = "\
+#character-encoding UTF-8
+#player1 alice Alice
+#player2 bob Bob
+>alice: ABDEKLY 8D BLEAK +24 24
+#note a solid start
+>bob: ?ADIOST H5 DOS.a +24 24
+>alice: DEYACHR - +0 24
+>bob: QVVWWXZ -QVVWW +0 24
+";
+
+    #[test]
+    fn test_import() {
+        let lexicon = Arc::new(Lexicon::from_words(["BLEAK", "DOSKA"]));
+        let session = read(&mut RECORD.as_bytes(), lexicon, Arc::new(Rules::standard()), 0).unwrap();
+
+        assert_eq!(session.players.len(), 2);
+        assert_eq!(session.players[0].score, 24);
+        assert_eq!(session.turn, 0);
+        assert_eq!(session.scoreless_turns, 2);
+        assert_eq!(session.bag.size(), 100 - 9);
+
+        assert_eq!(session.board.get_tile(7, 3), 'B');
+        assert_eq!(session.board.get_tile(7, 7), 'K');
+        assert_eq!(session.board.get_tile(8, 7), 'A');
+        assert!(session.board.is_blank(8, 7));
+        assert_eq!(
+            session.history[1].action,
+            Action::Play { word: "DOSKa".to_string(), row: 4, col: 7, across: false }
+        );
+        assert_eq!(session.history[1].rack, vec!['*', 'A', 'D', 'I', 'O', 'S', 'T']);
+        assert_eq!(session.history[3].action, Action::Exchange(vec!['Q', 'V', 'V', 'W', 'W']));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lexicon = Arc::new(Lexicon::from_words(["BLEAK"]));
+        let session = read(&mut RECORD.as_bytes(), lexicon.clone(), Arc::new(Rules::standard()), 0).unwrap();
+
+        let mut bytes = Vec::new();
+        write(&session, &mut bytes).unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.contains("#player1 alice Alice\n"));
+        assert!(text.contains(">alice: ABDEKLY 8D BLEAK +24 24\n"));
+        assert!(text.contains(">bob: ?ADIOST H5 DOS.a +24 24\n"));
+        assert!(text.contains(">bob: QVVWWXZ -QVVWW +0 24\n"));
+
+        let reloaded = read(&mut bytes.as_slice(), lexicon, Arc::new(Rules::standard()), 0).unwrap();
+        assert_eq!(reloaded.history, session.history);
+        assert_eq!(reloaded.board.get_board_chars(), session.board.get_board_chars());
+    }
+
+    #[test]
+    fn test_analyse_imported_game() {
+        let session = read(&mut RECORD.as_bytes(), Arc::new(Lexicon::from_words(["BLEAK", "BLEAKS"])), Arc::new(Rules::standard()), 0).unwrap();
+
+        let rack = Rack::from_tiles("S");
+        let word_choice = find_greediest_word(&session.board, &rack).unwrap();
+        assert_eq!(word_choice.word, "BLEAKS");
+    }
+
+    #[test]
+    fn test_end_of_game() {
+        let record = ">p1: QI 8G QI +22 22\n>p1: (VV) +8 30\n>p2: (VV) -8 -8\n";
+        let session = read(&mut record.as_bytes(), Arc::new(Lexicon::from_words(["QI"])), Arc::new(Rules::standard()), 0).unwrap();
+
+        assert_eq!(session.players[0].score, 30);
+        assert_eq!(session.players[1].score, -8);
+        assert_eq!(session.history[2].action, Action::EndRack(vec!['V', 'V']));
+    }
+
+    #[test]
+    fn test_withdrawn_play() {
+        let record = "\
+>p1: ABDEKLY 8D BLEAK +24 24
+>p2: AEINRST H4 RAIN. +20 20
+>p2: AEINRST -- -20 0
+>p1: ADEHLOY - +0 24
+>p2: ADIOSTU (challenge) +5 5
+>p1: (time) -10 14
+";
+        let lexicon = Arc::new(Lexicon::from_words(["BLEAK"]));
+        let session = read(&mut record.as_bytes(), lexicon.clone(), Arc::new(Rules::standard()), 0).unwrap();
+
+        // the phony's tiles are back in the bag
+        assert_eq!(session.board.get_tile(3, 7), '-');
+        assert_eq!(session.board.get_tile(7, 7), 'K');
+        assert_eq!(session.bag.size(), 100 - 5);
+        assert_eq!(session.history[2].action, Action::Withdrawn);
+        assert_eq!(session.history[4].action, Action::ChallengeBonus);
+        assert_eq!(session.history[5].action, Action::TimePenalty);
+        assert_eq!(session.players[0].score, 14);
+        assert_eq!(session.players[1].score, 5);
+
+        let mut bytes = Vec::new();
+        write(&session, &mut bytes).unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.contains(">p2: AEINRST -- -20 0\n"));
+        assert!(text.contains(">p2: ADIOSTU (challenge) +5 5\n"));
+        assert!(text.contains(">p1: (time) -10 14\n"));
+
+        let reloaded = read(&mut bytes.as_slice(), lexicon, Arc::new(Rules::standard()), 0).unwrap();
+        assert_eq!(reloaded.history, session.history);
+        assert_eq!(reloaded.board.get_board_chars(), session.board.get_board_chars());
+
+        let record = ">p1: AEINRST -- -20 0\n";
+        assert!(read(&mut record.as_bytes(), Arc::new(Lexicon::from_words(["BLEAK"])), Arc::new(Rules::standard()), 0).is_err());
+    }
+
+    #[test]
+    fn test_bag_from_seed() {
+        let read_bag = |seed| {
+            let lexicon = Arc::new(Lexicon::from_words(["BLEAK"]));
+            read(&mut RECORD.as_bytes(), lexicon, Arc::new(Rules::standard()), seed).unwrap().bag.tiles
+        };
+        assert_eq!(read_bag(7), read_bag(7));
+        assert_ne!(read_bag(7), read_bag(8));
+    }
+
+    #[test]
+    fn test_no_rack() {
+        let record = ">p1:  8G QI +22 22\n>p1:  - +0 22\n";
+        let session = read(&mut record.as_bytes(), Arc::new(Lexicon::from_words(["QI"])), Arc::new(Rules::standard()), 0).unwrap();
+
+        assert_eq!(session.board.get_tile(7, 6), 'Q');
+        assert!(session.history[0].rack.is_empty());
+        assert_eq!(session.history[1].action, Action::Pass);
+    }
+}
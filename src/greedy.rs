@@ -1,247 +1,529 @@
-use crate::game::{Board, Rack};
+use crate::game::{blank_for, rack_tile, Board, Rack, RACK_SIZE};
+use crate::lexicon::{Dawg, NodeId};
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct WordChoice {
     pub word: String,
     pub score: i32,
     pub row: usize,
     pub col: usize,
     pub across: bool,
+    // the tiles taken from the rack, in board order. Blanks are '*' here and
+    // lowercase in word.
+    pub tiles: Vec<char>,
+    // words formed perpendicular to the main word.
+    pub cross_words: Vec<String>,
 }
 
-pub fn find_greediest_word(board: &mut Board, rack: &Rack) -> WordChoice {
-    // to play the greediest word, we will need to search all of the available
-    // neighbors on the board.
+// Every legal play for a rack, in the order they were generated.
+#[derive(Clone, Debug, Default)]
+pub struct MoveList {
+    moves: Vec<WordChoice>,
+}
 
-    // for each neighbor, we pick a tile, then verify that the tile is some substring
-    // in a dictionary word. (I'm scared because that sounds like a tricky search).
+impl MoveList {
+    // highest score first. Plays with equal scores keep their relative order.
+    pub fn sorted_by_score(mut self) -> Self {
+        self.moves.sort_by_key(|choice| std::cmp::Reverse(choice.score));
+        self
+    }
 
-    // Once we've verified that there is at least one potential dictionary word.
-    let mut best_play= WordChoice {word: String::new(), score: 0, row: 15, col: 15, across: false};
-    for neighbor in board.get_neighbors() {
-        let best_for_here = find_words(board, rack, neighbor.0, neighbor.1, best_play.score);
-        if best_for_here.score > best_play.score {
-            best_play = best_for_here;
-        }
+    pub fn sorted_by<C>(mut self, compare: C) -> Self
+    where
+        C: FnMut(&WordChoice, &WordChoice) -> std::cmp::Ordering,
+    {
+        self.moves.sort_by(compare);
+        self
     }
 
-    best_play
-}
+    pub fn filter<P>(mut self, predicate: P) -> Self
+    where
+        P: FnMut(&WordChoice) -> bool,
+    {
+        self.moves.retain(predicate);
+        self
+    }
 
-fn find_words_across(
-    board: &mut Board,
-    tiles: &Vec<char>,
-    row: usize,
-    col: usize,
-    best: &mut WordChoice,
-) {
-    let across_candidates;
-    if board.get_tile(row, col) == '-' {
-        across_candidates = vec![(row, col)];
-    } else {
-        across_candidates = get_across_candidates(board, row, col);
+    pub fn top(mut self, n: usize) -> Self {
+        self.moves.truncate(n);
+        self
     }
 
-    let substr = board.get_word_across(row, col).unwrap_or(String::new());
-    if !board.is_word_down(row, col) || !board.substr_promising(&substr) {
-        return;
+    pub fn best(&self) -> Option<&WordChoice> {
+        self.moves.iter().reduce(|best, choice| if choice.score > best.score { choice } else { best })
     }
 
-    if board.is_word_across(row, col) {
-        let score = board.score();
-        if score > best.score {
-            best.word = substr.clone();
-            best.score = score;
-            best.row = row;
-            best.col = board.get_leftmost_col(row, col).unwrap();
-            best.across = true;
-        }
+    pub fn iter(&self) -> std::slice::Iter<'_, WordChoice> {
+        self.moves.iter()
     }
 
-    if tiles.is_empty() {
-        return;
+    pub fn len(&self) -> usize {
+        self.moves.len()
     }
 
-    for candidate in across_candidates {
-        for (i, letter) in tiles.iter().enumerate() {
-            board.put_tile(*letter, candidate.0, candidate.1);
-            let mut tiles_copy = tiles.clone();
-            tiles_copy.remove(i);
-            find_words_across(board, &tiles_copy, candidate.0, candidate.1, best);
-            board.remove_tile(candidate.0, candidate.1);
-        }
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<WordChoice> {
+        self.moves
     }
 }
 
-fn find_words_down(
-    board: &mut Board,
-    tiles: &Vec<char>,
-    row: usize,
-    col: usize,
-    best: &mut WordChoice,
-) {
-    let down_candidates;
-    if board.get_tile(row, col) == '-' {
-        down_candidates = vec![(row, col)];
-    } else {
-        down_candidates = get_down_candidates(board, row, col);
+impl IntoIterator for MoveList {
+    type Item = WordChoice;
+    type IntoIter = std::vec::IntoIter<WordChoice>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.into_iter()
     }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a WordChoice;
+    type IntoIter = std::slice::Iter<'a, WordChoice>;
 
-    let substr = board.get_word_down(row, col).unwrap_or(String::new());
-    if !board.is_word_across(row, col) || !board.substr_promising(&substr) {
-        return;
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.iter()
     }
+}
 
-    if board.is_word_down(row, col) {
-        let score = board.score();
-        if score > best.score {
-            best.word = substr.clone();
-            best.score = score;
-            best.row = board.get_upmost_row(row, col).unwrap();
-            best.col = col;
-            best.across = false;
+pub fn find_all_words(board: &Board, rack: &Rack) -> MoveList {
+    let mut moves = Vec::new();
+    generate_moves(board, rack, |choice| moves.push(choice));
+    MoveList { moves }
+}
+
+// The highest scoring play, or None if there is no play at all. A play that
+// scores nothing, e.g. one made only of blanks, is still a play.
+pub fn find_greediest_word(board: &Board, rack: &Rack) -> Option<WordChoice> {
+    let mut best_play: Option<WordChoice> = None;
+    generate_moves(board, rack, |choice| {
+        if best_play.as_ref().is_none_or(|best| choice.score > best.score) {
+            best_play = Some(choice);
         }
-    }
+    });
 
-    if tiles.is_empty() {
-        return;
-    }
+    best_play
+}
 
-    for candidate in down_candidates {
-        for (i, letter) in tiles.iter().enumerate() {
-            board.put_tile(*letter, candidate.0, candidate.1);
-            let mut tiles_copy = tiles.clone();
-            tiles_copy.remove(i);
-            find_words_down(board, &tiles_copy, candidate.0, candidate.1, best);
-            board.remove_tile(candidate.0, candidate.1);
+// Appel & Jacobson, "The World's Fastest Scrabble Program".
+//
+// Every play has to cover at least one anchor, i.e. one of the board's
+// neighbor squares. For each anchor we build every prefix that can sit to the
+// left of it, then extend rightwards through the anchor following the word
+// graph, only placing letters that also form valid words in the other
+// direction (the cross-checks). Down plays are found the same way by walking
+// columns instead of rows.
+fn generate_moves<F>(board: &Board, rack: &Rack, mut on_play: F)
+where
+    F: FnMut(WordChoice),
+{
+    let mut anchors = board.get_neighbors();
+    anchors.sort();
+
+    for across in [true, false] {
+        let mut generator = Generator::new(board, rack, across, &mut on_play);
+        for line in 0..board.size() {
+            generator.set_line(line);
+            for &(row, col) in &anchors {
+                let (anchor_line, anchor) = if across { (row, col) } else { (col, row) };
+                if anchor_line == line {
+                    generator.generate_at(anchor);
+                }
+            }
         }
     }
 }
 
-fn find_words(
-    board: &mut Board,
-    rack: &Rack,
-    row: usize,
-    col: usize,
-    best_score: i32,
-) -> WordChoice {
-    let mut best_across = WordChoice {word: String::new(), score: best_score, row: 15, col: 15, across: true};
-    find_words_across(board, &rack.get_tiles_vec(), row, col, &mut best_across);
-
-    let mut best_down = WordChoice {word: String::new(), score: best_score, row: 15, col: 15, across: false};
-    find_words_down(board, &rack.get_tiles_vec(), row, col, &mut best_down);
+// What may be placed on an empty square given the tiles above and below it
+// (or left and right of it, for down plays).
+struct CrossCheck {
+    // None if the square has no perpendicular neighbors, so any letter fits.
+    allowed: Option<Vec<char>>,
+    // value of the existing tiles in the perpendicular word.
+    sum: i32,
+    // the existing tiles of the perpendicular word on either side.
+    before: String,
+    after: String,
+}
 
-    if best_across.score > best_down.score {
-        best_across
-    } else {
-        best_down
+impl CrossCheck {
+    fn allows(&self, letter: char) -> bool {
+        match &self.allowed {
+            Some(letters) => letters.contains(&letter),
+            None => true,
+        }
     }
 }
 
-fn get_across_candidates(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
+struct Generator<'a, F> {
+    board: &'a Board,
+    dawg: &'a Dawg,
+    size: usize,
+    across: bool,
+    line: usize,
+    cross_checks: Vec<CrossCheck>,
+    // tiles placed on the current line by the play under construction.
+    placed: Vec<Option<char>>,
+    rack: Vec<(char, usize)>,
+    on_play: &'a mut F,
+}
 
-    let leftmost = board.get_leftmost_col(row, col).unwrap();
-    let rightmost = board.get_rightmost_col(row, col).unwrap();
+impl<'a, F> Generator<'a, F>
+where
+    F: FnMut(WordChoice),
+{
+    fn new(board: &'a Board, rack: &Rack, across: bool, on_play: &'a mut F) -> Self {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for tile in rack.get_tiles_vec() {
+            match counts.iter_mut().find(|(letter, _)| *letter == tile) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((tile, 1)),
+            }
+        }
 
-    result.append(&mut get_across_neighbors(board, row, leftmost));
+        let size = board.size();
+        Generator {
+            board,
+            dawg: board.lexicon().dawg(),
+            size,
+            across,
+            line: 0,
+            cross_checks: Vec::new(),
+            placed: vec![None; size],
+            rack: counts,
+            on_play,
+        }
+    }
 
-    if leftmost != rightmost {
-        result.append(&mut get_across_neighbors(board, row, rightmost));
+    // (row, col) of position pos along the current line.
+    fn square(&self, pos: usize) -> (usize, usize) {
+        if self.across {
+            (self.line, pos)
+        } else {
+            (pos, self.line)
+        }
     }
 
-    result
-}
+    fn tile_at(&self, pos: usize) -> char {
+        let (row, col) = self.square(pos);
+        self.board.get_tile(row, col)
+    }
 
-fn get_across_neighbors(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
+    fn is_empty(&self, pos: usize) -> bool {
+        self.tile_at(pos) == '-'
+    }
 
-    let top_edge = row == 0;
-    let bottom_edge = row == 14;
-    let left_edge = col == 0;
-    let right_edge = col == 14;
+    fn set_line(&mut self, line: usize) {
+        self.line = line;
+        self.cross_checks = (0..self.size).map(|pos| self.cross_check(pos)).collect();
+    }
 
-    // There's probably a better way to do this, but I just went with this.
-    if left_edge {
-        // non-corner left
-        if board.get_tile(row, col + 1) == '-' {
-            result.push((row, col + 1));
+    fn cross_check(&self, pos: usize) -> CrossCheck {
+        let (row, col) = self.square(pos);
+        if self.board.get_tile(row, col) != '-' {
+            return CrossCheck { allowed: Some(Vec::new()), sum: 0, before: String::new(), after: String::new() };
         }
-    } else if bottom_edge && right_edge {
-        // bottom-right corner
-        if board.get_tile(row, col - 1) == '-' {
-            result.push((row, col - 1));
+
+        // perpendicular squares before and after this one
+        let step = |offset: isize| -> Option<(usize, usize)> {
+            let (r, c) = if self.across {
+                (row as isize + offset, col as isize)
+            } else {
+                (row as isize, col as isize + offset)
+            };
+            if r < 0 || c < 0 || r as usize >= self.size || c as usize >= self.size {
+                return None;
+            }
+            let (r, c) = (r as usize, c as usize);
+            if self.board.get_tile(r, c) == '-' {
+                None
+            } else {
+                Some((r, c))
+            }
+        };
+
+        let mut sum = 0;
+        let mut before = String::new();
+        let mut offset = -1;
+        while let Some((r, c)) = step(offset) {
+            before.insert(0, self.board.get_tile(r, c));
+            sum += self.board.get_val(r, c);
+            offset -= 1;
         }
-    } else if bottom_edge {
-        // non-corner bottom
-        if board.get_tile(row, col - 1) == '-' {
-            result.push((row, col - 1));
+        let mut after = String::new();
+        let mut offset = 1;
+        while let Some((r, c)) = step(offset) {
+            after.push(self.board.get_tile(r, c));
+            sum += self.board.get_val(r, c);
+            offset += 1;
         }
-        if board.get_tile(row, col + 1) == '-' {
-            result.push((row, col + 1));
+
+        if before.is_empty() && after.is_empty() {
+            return CrossCheck { allowed: None, sum: 0, before, after };
         }
-    } else if right_edge {
-        // non-corner right
-        if board.get_tile(row, col - 1) == '-' {
-            result.push((row, col - 1));
+
+        let mut allowed = Vec::new();
+        if let Some(node) = self.dawg.walk(&before) {
+            for (letter, child) in self.dawg.edges(node) {
+                if let Some(end) = self.dawg.walk_from(child, &after)
+                    && self.dawg.is_terminal(end)
+                {
+                    allowed.push(letter);
+                }
+            }
         }
-    } else if top_edge {
-        // non-corner top
-        if board.get_tile(row, col - 1) == '-' {
-            result.push((row, col - 1));
+        CrossCheck { allowed: Some(allowed), sum, before, after }
+    }
+
+    fn generate_at(&mut self, anchor: usize) {
+        if anchor > 0 && !self.is_empty(anchor - 1) {
+            // the left part is already on the board
+            let mut start = anchor;
+            let mut prefix = String::new();
+            while start > 0 && !self.is_empty(start - 1) {
+                start -= 1;
+                prefix.insert(0, self.tile_at(start));
+            }
+            if let Some(node) = self.dawg.walk(&prefix) {
+                self.extend_right(node, anchor, anchor, start);
+            }
+            return;
         }
-        if board.get_tile(row, col + 1) == '-' {
-            result.push((row, col + 1));
+
+        // the left part may use any empty squares up to the previous anchor,
+        // none of which have cross-checks.
+        let mut limit = 0;
+        while limit < anchor {
+            let pos = anchor - limit - 1;
+            if !self.is_empty(pos) || self.cross_checks[pos].allowed.is_some() || self.is_anchor(pos) {
+                break;
+            }
+            limit += 1;
         }
-    } else {
-        // non-edge case
-        if board.get_tile(row, col - 1) == '-' {
-            result.push((row, col - 1));
+
+        let mut left = Vec::new();
+        self.left_part(self.dawg.root(), &mut left, limit, anchor);
+    }
+
+    fn is_anchor(&self, pos: usize) -> bool {
+        let (row, col) = self.square(pos);
+        self.board.get_tile(row, col) == '-' && self.board.is_neighbor(row, col)
+    }
+
+    fn left_part(&mut self, node: NodeId, left: &mut Vec<char>, limit: usize, anchor: usize) {
+        let start = anchor - left.len();
+        for (i, letter) in left.iter().enumerate() {
+            self.placed[start + i] = Some(*letter);
         }
-        if board.get_tile(row, col + 1) == '-' {
-            result.push((row, col + 1));
+        self.extend_right(node, anchor, anchor, start);
+        for i in 0..left.len() {
+            self.placed[start + i] = None;
         }
-    }
 
-    result
-}
+        if limit == 0 {
+            return;
+        }
 
-fn get_down_candidates(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
+        let dawg = self.dawg;
+        for (letter, child) in dawg.edges(node) {
+            for tile in [letter, blank_for(letter)] {
+                if self.take_tile(rack_tile(tile)) {
+                    left.push(tile);
+                    self.left_part(child, left, limit - 1, anchor);
+                    left.pop();
+                    self.return_tile(rack_tile(tile));
+                }
+            }
+        }
+    }
 
-    let upmost = board.get_upmost_row(row, col).unwrap();
-    let downmost = board.get_downmost_row(row, col).unwrap();
+    fn extend_right(&mut self, node: NodeId, pos: usize, anchor: usize, start: usize) {
+        if pos < self.size && !self.is_empty(pos) {
+            if let Some(child) = self.dawg.child(node, self.tile_at(pos)) {
+                self.extend_right(child, pos + 1, anchor, start);
+            }
+            return;
+        }
 
-    result.append(&mut get_down_neighbors(board, upmost, col));
+        // one letter words are not allowed
+        if pos > anchor && pos - start > 1 && self.dawg.is_terminal(node) {
+            self.record(start, pos);
+        }
 
-    if upmost != downmost {
-        result.append(&mut get_down_neighbors(board, downmost, col));
+        if pos >= self.size {
+            return;
+        }
+
+        let dawg = self.dawg;
+        for (letter, child) in dawg.edges(node) {
+            if !self.cross_checks[pos].allows(letter) {
+                continue;
+            }
+            // try the letter itself, then a blank standing in for it
+            for tile in [letter, blank_for(letter)] {
+                if self.take_tile(rack_tile(tile)) {
+                    self.placed[pos] = Some(tile);
+                    self.extend_right(child, pos + 1, anchor, start);
+                    self.placed[pos] = None;
+                    self.return_tile(rack_tile(tile));
+                }
+            }
+        }
     }
 
-    result
-}
+    fn take_tile(&mut self, letter: char) -> bool {
+        match self.rack.iter_mut().find(|(tile, count)| *tile == letter && *count > 0) {
+            Some((_, count)) => {
+                *count -= 1;
+                true
+            }
+            None => false,
+        }
+    }
 
-fn get_down_neighbors(board: &Board, row: usize, col: usize) -> Vec<(usize, usize)> {
-    let mut result = Vec::new();
+    fn return_tile(&mut self, letter: char) {
+        if let Some((_, count)) = self.rack.iter_mut().find(|(tile, _)| *tile == letter) {
+            *count += 1;
+        }
+    }
 
-    let top_edge = row == 0;
-    let bottom_edge = row == 14;
+    // the word runs from start up to, but not including, end.
+    fn record(&mut self, start: usize, end: usize) {
+        // a single tile that also makes an across word was already found by
+        // the across pass.
+        if !self.across {
+            let mut placed = (start..end).filter(|pos| self.placed[*pos].is_some());
+            if let (Some(pos), None) = (placed.next(), placed.next())
+                && self.cross_checks[pos].allowed.is_some()
+            {
+                return;
+            }
+        }
 
-    // There's probably a better way to do this, but I just went with this.
-    if top_edge {
-        if board.get_tile(row + 1, col) == '-' {
-            result.push((row + 1, col));
+        let mut word = String::new();
+        let mut tiles = Vec::new();
+        let mut cross_words = Vec::new();
+        let mut main_score = 0;
+        let mut word_mult = 1;
+        let mut cross_score_sum = 0;
+        let mut count = 0;
+
+        for pos in start..end {
+            let (row, col) = self.square(pos);
+            match self.placed[pos] {
+                Some(letter) => {
+                    word.push(letter);
+                    tiles.push(rack_tile(letter));
+                    count += 1;
+
+                    let letter_score = self.board.rules().tile_set.score_letter(letter) * self.board.letter_mult(row, col);
+                    main_score += letter_score;
+                    word_mult *= self.board.word_mult(row, col);
+
+                    let cross_check = &self.cross_checks[pos];
+                    if cross_check.allowed.is_some() {
+                        cross_score_sum += (cross_check.sum + letter_score) * self.board.word_mult(row, col);
+                        cross_words.push(format!("{}{}{}", cross_check.before, letter, cross_check.after));
+                    }
+                }
+                None => {
+                    word.push(self.board.get_tile(row, col));
+                    main_score += self.board.get_val(row, col);
+                }
+            }
         }
-    } else if bottom_edge {
-        if board.get_tile(row - 1, col) == '-' {
-            result.push((row - 1, col));
+
+        let mut score = main_score * word_mult + cross_score_sum;
+        if count >= RACK_SIZE {
+            score += self.board.rules().bingo_bonus;
         }
-    } else {
-        if board.get_tile(row - 1, col) == '-' {
-            result.push((row - 1, col));
+
+        let (row, col) = self.square(start);
+        (self.on_play)(WordChoice { word, score, row, col, across: self.across, tiles, cross_words });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opening() {
+        let board = Board::new("dict.txt".to_string());
+
+        // ZAX doubled on the center square
+        let choice = find_greediest_word(&board, &Rack::from_tiles("ZAXQQVV")).unwrap();
+        assert_eq!(choice.word, "ZAX");
+        assert_eq!(choice.score, 38);
+        assert!(choice.across);
+        assert_eq!(choice.row, 7);
+    }
+
+    #[test]
+    fn test_scoreless_play() {
+        let board = Board::new("dict.txt".to_string());
+
+        // two blanks score nothing, but they are still a play
+        let choice = find_greediest_word(&board, &Rack::from_tiles("**")).unwrap();
+        assert_eq!(choice.score, 0);
+        assert_eq!(choice.tiles, vec!['*', '*']);
+        assert!(find_greediest_word(&board, &Rack::from_tiles("QV")).is_none());
+    }
+
+    #[test]
+    fn test_all_moves() {
+        let mut board = Board::new("dict.txt".to_string());
+        board.write_across(String::from("leavy"), 7, 7).unwrap();
+        assert_eq!(board.submit().unwrap(), 30);
+
+        let moves = find_all_words(&board, &Rack::from_tiles("FONDLYE"));
+        let best = moves.best().unwrap().clone();
+
+        let top = moves.clone().sorted_by_score().top(5);
+        assert_eq!(top.len(), 5);
+        assert_eq!(top.iter().next().unwrap().score, best.score);
+        assert!(top.iter().zip(top.iter().skip(1)).all(|(a, b)| a.score >= b.score));
+
+        let down = moves.clone().filter(|choice| !choice.across);
+        assert!(!down.is_empty());
+        assert!(down.iter().all(|choice| !choice.across));
+
+        // every play reports the rack tiles it used and the words it formed
+        for choice in &moves {
+            assert!(!choice.tiles.is_empty());
+            assert!(choice.tiles.len() <= choice.word.len());
+            for word in &choice.cross_words {
+                assert!(board.word_in_dict(word.clone()), "{word}");
+            }
         }
-        if board.get_tile(row + 1, col) == '-' {
-            result.push((row + 1, col));
+    }
+
+    #[test]
+    fn test_scores_match_board() {
+        let mut board = Board::new("dict.txt".to_string());
+        board.write_across(String::from("leavy"), 7, 7).unwrap();
+        assert_eq!(board.submit().unwrap(), 30);
+        board.write_across(String::from("oration"), 8, 2).unwrap();
+        assert_eq!(board.submit().unwrap(), 65);
+
+        let rack = Rack::from_tiles("FONDLY*");
+        let mut plays = Vec::new();
+        generate_moves(&board, &rack, |choice| plays.push(choice));
+        assert!(plays.iter().any(|choice| choice.tiles.contains(&'*')));
+
+        for choice in plays {
+            let mut rack = Rack::from_tiles("FONDLY*");
+            if choice.across {
+                board.write_across_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col).unwrap();
+            } else {
+                board.write_down_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col).unwrap();
+            }
+            assert!(board.is_valid(), "{} should be valid", choice.word);
+            assert_eq!(board.score(), choice.score, "score of {} at {} {} {}", choice.word, choice.row, choice.col, choice.across);
+            board.unstage_to_rack(&mut rack);
+            assert_eq!(rack.get_tiles_vec(), Rack::from_tiles("FONDLY*").get_tiles_vec());
         }
     }
-    result
 }
@@ -0,0 +1,301 @@
+// Board layouts: how big the board is, where its premium squares are and
+// which square the first play has to cover. Layouts are plain text, one row
+// of squares per line:
+//
+//   # comments start with '#'
+//   start 7 7
+//   tw -- -- dl -- ...
+//
+// Squares are "--" for a plain square, "dl"/"tl"/"ql" for double, triple and
+// quadruple letter and "dw"/"tw"/"qw" for double, triple and quadruple word.
+// The board is square, and the start line is optional: without it the first
+// play covers the middle.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const STANDARD: &str = "
+tw -- -- dl -- -- -- tw -- -- -- dl -- -- tw
+-- dw -- -- -- tl -- -- -- tl -- -- -- dw --
+-- -- dw -- -- -- dl -- dl -- -- -- dw -- --
+dl -- -- dw -- -- -- dl -- -- -- dw -- -- dl
+-- -- -- -- dw -- -- -- -- -- dw -- -- -- --
+-- tl -- -- -- tl -- -- -- tl -- -- -- tl --
+-- -- dl -- -- -- dl -- dl -- -- -- dl -- --
+tw -- -- dl -- -- -- dw -- -- -- dl -- -- tw
+-- -- dl -- -- -- dl -- dl -- -- -- dl -- --
+-- tl -- -- -- tl -- -- -- tl -- -- -- tl --
+-- -- -- -- dw -- -- -- -- -- dw -- -- -- --
+dl -- -- dw -- -- -- dl -- -- -- dw -- -- dl
+-- -- dw -- -- -- dl -- dl -- -- -- dw -- --
+-- dw -- -- -- tl -- -- -- tl -- -- -- dw --
+tw -- -- dl -- -- -- tw -- -- -- dl -- -- tw
+";
+
+// Words With Friends. The middle square is plain.
+const WWF: &str = "
+-- -- -- tw -- -- tl -- tl -- -- tw -- -- --
+-- -- dl -- -- dw -- -- -- dw -- -- dl -- --
+-- dl -- -- dl -- -- -- -- -- dl -- -- dl --
+tw -- -- tl -- -- -- dw -- -- -- tl -- -- tw
+-- -- dl -- -- -- dl -- dl -- -- -- dl -- --
+-- dw -- -- -- tl -- -- -- tl -- -- -- dw --
+tl -- -- -- dl -- -- -- -- -- dl -- -- -- tl
+-- -- -- dw -- -- -- -- -- -- -- dw -- -- --
+tl -- -- -- dl -- -- -- -- -- dl -- -- -- tl
+-- dw -- -- -- tl -- -- -- tl -- -- -- dw --
+-- -- dl -- -- -- dl -- dl -- -- -- dl -- --
+tw -- -- tl -- -- -- dw -- -- -- tl -- -- tw
+-- dl -- -- dl -- -- -- -- -- dl -- -- dl --
+-- -- dl -- -- dw -- -- -- dw -- -- dl -- --
+-- -- -- tw -- -- tl -- tl -- -- tw -- -- --
+";
+
+// The 21x21 board for the super-sized game, with quadruple word squares in
+// the corners.
+const SUPER: &str = "
+qw -- -- dl -- -- -- tw -- -- dl -- -- tw -- -- -- dl -- -- qw
+-- dw -- -- tl -- -- -- dw -- -- -- dw -- -- -- tl -- -- dw --
+-- -- dw -- -- ql -- -- -- dw -- dw -- -- -- ql -- -- dw -- --
+dl -- -- tw -- -- dl -- -- -- tw -- -- -- dl -- -- tw -- -- dl
+-- tl -- -- dw -- -- -- tl -- -- -- tl -- -- -- dw -- -- tl --
+-- -- ql -- -- dw -- -- -- dl -- dl -- -- -- dw -- -- ql -- --
+-- -- -- dl -- -- dw -- -- -- dl -- -- -- dw -- -- dl -- -- --
+tw -- -- -- -- -- -- dw -- -- -- -- -- dw -- -- -- -- -- -- tw
+-- dw -- -- tl -- -- -- tl -- -- -- tl -- -- -- tl -- -- dw --
+-- -- dw -- -- dl -- -- -- dl -- dl -- -- -- dl -- -- dw -- --
+dl -- -- tw -- -- dl -- -- -- dw -- -- -- dl -- -- tw -- -- dl
+-- -- dw -- -- dl -- -- -- dl -- dl -- -- -- dl -- -- dw -- --
+-- dw -- -- tl -- -- -- tl -- -- -- tl -- -- -- tl -- -- dw --
+tw -- -- -- -- -- -- dw -- -- -- -- -- dw -- -- -- -- -- -- tw
+-- -- -- dl -- -- dw -- -- -- dl -- -- -- dw -- -- dl -- -- --
+-- -- ql -- -- dw -- -- -- dl -- dl -- -- -- dw -- -- ql -- --
+-- tl -- -- dw -- -- -- tl -- -- -- tl -- -- -- dw -- -- tl --
+dl -- -- tw -- -- dl -- -- -- tw -- -- -- dl -- -- tw -- -- dl
+-- -- dw -- -- ql -- -- -- dw -- dw -- -- -- ql -- -- dw -- --
+-- dw -- -- tl -- -- -- dw -- -- -- dw -- -- -- tl -- -- dw --
+qw -- -- dl -- -- -- tw -- -- dl -- -- tw -- -- -- dl -- -- qw
+";
+
+// the layouts that can be chosen by name.
+pub const BUILT_IN: [&str; 3] = ["standard", "wwf", "super"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Premium {
+    None,
+    DoubleLetter,
+    TripleLetter,
+    QuadrupleLetter,
+    DoubleWord,
+    TripleWord,
+    QuadrupleWord,
+}
+
+impl Premium {
+    pub fn letter_mult(self) -> i32 {
+        match self {
+            Premium::DoubleLetter => 2,
+            Premium::TripleLetter => 3,
+            Premium::QuadrupleLetter => 4,
+            _ => 1,
+        }
+    }
+
+    pub fn word_mult(self) -> i32 {
+        match self {
+            Premium::DoubleWord => 2,
+            Premium::TripleWord => 3,
+            Premium::QuadrupleWord => 4,
+            _ => 1,
+        }
+    }
+
+    // how the square is written in layouts and shown on the board.
+    pub fn code(self) -> &'static str {
+        match self {
+            Premium::None => "--",
+            Premium::DoubleLetter => "dl",
+            Premium::TripleLetter => "tl",
+            Premium::QuadrupleLetter => "ql",
+            Premium::DoubleWord => "dw",
+            Premium::TripleWord => "tw",
+            Premium::QuadrupleWord => "qw",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "--" => Some(Premium::None),
+            "dl" => Some(Premium::DoubleLetter),
+            "tl" => Some(Premium::TripleLetter),
+            "ql" => Some(Premium::QuadrupleLetter),
+            "dw" => Some(Premium::DoubleWord),
+            "tw" => Some(Premium::TripleWord),
+            "qw" => Some(Premium::QuadrupleWord),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    squares: Vec<Vec<Premium>>,
+    // the square the first play has to cover.
+    pub start: (usize, usize),
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::standard()
+    }
+}
+
+impl Layout {
+    pub fn standard() -> Self {
+        Layout::parse(STANDARD).expect("The standard layout is valid.")
+    }
+
+    pub fn wwf() -> Self {
+        Layout::parse(WWF).expect("The WWF layout is valid.")
+    }
+
+    pub fn super_sized() -> Self {
+        Layout::parse(SUPER).expect("The super layout is valid.")
+    }
+
+    // a built-in layout by name, see BUILT_IN.
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Layout::standard()),
+            "wwf" => Some(Layout::wwf()),
+            "super" => Some(Layout::super_sized()),
+            _ => None,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Layout::parse(&fs::read_to_string(path)?)
+    }
+
+    // A built-in layout if there is one with this name, otherwise the layout
+    // in the file at this path.
+    pub fn load(name_or_path: &str) -> io::Result<Self> {
+        match Layout::built_in(name_or_path) {
+            Some(layout) => Ok(layout),
+            None => Layout::from_file(name_or_path),
+        }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut squares = Vec::new();
+        let mut start = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || invalid_data(&format!("bad layout line {}: '{line}'", number + 1));
+
+            if let Some(rest) = line.strip_prefix("start") {
+                let fields: Vec<usize> = rest
+                    .split_whitespace()
+                    .map(|field| field.parse().map_err(|_| bad_line()))
+                    .collect::<io::Result<_>>()?;
+                let [row, col] = fields[..] else {
+                    return Err(bad_line());
+                };
+                start = Some((row, col));
+                continue;
+            }
+
+            let row = line
+                .split_whitespace()
+                .map(|code| Premium::from_code(code).ok_or_else(bad_line))
+                .collect::<io::Result<Vec<_>>>()?;
+            squares.push(row);
+        }
+
+        let size = squares.len();
+        if size == 0 {
+            return Err(invalid_data("the layout has no squares"));
+        }
+        if squares.iter().any(|row| row.len() != size) {
+            return Err(invalid_data(&format!("the layout is not {size} squares wide on every row")));
+        }
+        let start = start.unwrap_or((size / 2, size / 2));
+        if start.0 >= size || start.1 >= size {
+            return Err(invalid_data("the start square is off the board"));
+        }
+
+        Ok(Layout { squares, start })
+    }
+
+    pub fn size(&self) -> usize {
+        self.squares.len()
+    }
+
+    pub fn premium(&self, row: usize, col: usize) -> Premium {
+        self.squares[row][col]
+    }
+}
+
+// Writes the layout in the format parse reads.
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "start {} {}", self.start.0, self.start.1)?;
+        for row in &self.squares {
+            let codes: Vec<&str> = row.iter().map(|premium| premium.code()).collect();
+            writeln!(f, "{}", codes.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard() {
+        let layout = Layout::standard();
+        assert_eq!(layout.size(), 15);
+        assert_eq!(layout.start, (7, 7));
+        assert_eq!(layout.premium(0, 0), Premium::TripleWord);
+        assert_eq!(layout.premium(7, 7), Premium::DoubleWord);
+        assert_eq!(layout.premium(5, 5), Premium::TripleLetter);
+        assert_eq!(Layout::parse(&layout.to_string()).unwrap(), layout);
+
+        let wwf = Layout::built_in("wwf").unwrap();
+        assert_eq!(wwf.start, (7, 7));
+        assert_eq!(wwf.premium(7, 7), Premium::None);
+        assert_eq!(wwf.premium(0, 3), Premium::TripleWord);
+
+        let super_sized = Layout::built_in("super").unwrap();
+        assert_eq!(super_sized.size(), 21);
+        assert_eq!(super_sized.start, (10, 10));
+        assert_eq!(super_sized.premium(20, 0).word_mult(), 4);
+        assert_eq!(super_sized.premium(2, 5).letter_mult(), 4);
+        assert_eq!(Layout::parse(&super_sized.to_string()).unwrap(), super_sized);
+    }
+
+    #[test]
+    fn test_parse() {
+        let layout = Layout::parse("# tiny\nstart 0 2\ntw -- dl\n-- dw --\ndl -- tw\n").unwrap();
+        assert_eq!(layout.size(), 3);
+        assert_eq!(layout.start, (0, 2));
+        assert_eq!(layout.premium(2, 2).word_mult(), 3);
+        assert_eq!(layout.premium(0, 2).letter_mult(), 2);
+
+        assert_eq!(Layout::parse("-- --\n-- dw --\n").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(Layout::parse("-- xx\n-- --\n").is_err());
+        assert!(Layout::parse("start 5 5\n-- --\n-- --\n").is_err());
+        assert!(Layout::parse("# nothing\n").is_err());
+    }
+}
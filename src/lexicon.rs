@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::tileset::TileSet;
+
+// Marks the boundary between the reversed prefix and the suffix of a GADDAG path.
+pub const SEPARATOR: char = '>';
+
+// Derived indexes are cached next to the word list with this extension added.
+pub const CACHE_EXTENSION: &str = "gaddag";
+const CACHE_MAGIC: &[u8; 8] = b"WORDSGDG";
+const CACHE_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    first_edge: u32,
+    edge_count: u32,
+    terminal: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Edge {
+    letter: char,
+    target: u32,
+}
+
+// A minimized word graph. Every path from the root spells a prefix of some
+// word, and the nodes at the end of complete words are marked terminal.
+pub struct Dawg {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+impl Dawg {
+    // The words must be in ascending order. Duplicates are ignored.
+    pub fn from_sorted<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = Builder::new();
+        for word in words {
+            builder.insert(word.as_ref());
+        }
+        builder.finish()
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn is_terminal(&self, node: NodeId) -> bool {
+        self.nodes[node.0 as usize].terminal
+    }
+
+    pub fn child(&self, node: NodeId, letter: char) -> Option<NodeId> {
+        self.edge_slice(node)
+            .iter()
+            .find(|edge| edge.letter == letter)
+            .map(|edge| NodeId(edge.target))
+    }
+
+    pub fn edges(&self, node: NodeId) -> impl Iterator<Item = (char, NodeId)> + '_ {
+        self.edge_slice(node)
+            .iter()
+            .map(|edge| (edge.letter, NodeId(edge.target)))
+    }
+
+    // follow the letters of s starting at node.
+    pub fn walk_from(&self, node: NodeId, s: &str) -> Option<NodeId> {
+        let mut node = node;
+        for letter in s.chars() {
+            node = self.child(node, letter)?;
+        }
+        Some(node)
+    }
+
+    pub fn walk(&self, s: &str) -> Option<NodeId> {
+        self.walk_from(self.root(), s)
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        match self.walk(word) {
+            Some(node) => self.is_terminal(node),
+            None => false,
+        }
+    }
+
+    pub fn is_prefix(&self, prefix: &str) -> bool {
+        self.walk(prefix).is_some()
+    }
+
+    pub fn next_letters(&self, prefix: &str) -> Vec<char> {
+        match self.walk(prefix) {
+            Some(node) => self.edges(node).map(|(letter, _)| letter).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // all words in the graph, in ascending order.
+    pub fn words(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        let mut stack = vec![(self.root(), String::new())];
+        while let Some((node, prefix)) = stack.pop() {
+            if self.is_terminal(node) {
+                result.push(prefix.clone());
+            }
+            // push in reverse so the smallest letter is visited first
+            for edge in self.edge_slice(node).iter().rev() {
+                let mut next = prefix.clone();
+                next.push(edge.letter);
+                stack.push((NodeId(edge.target), next));
+            }
+        }
+        result
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_u32(writer, self.nodes.len() as u32)?;
+        write_u32(writer, self.edges.len() as u32)?;
+        for node in &self.nodes {
+            write_u32(writer, node.first_edge)?;
+            write_u32(writer, node.edge_count)?;
+            write_u32(writer, node.terminal as u32)?;
+        }
+        for edge in &self.edges {
+            write_u32(writer, edge.letter as u32)?;
+            write_u32(writer, edge.target)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let node_count = read_u32(reader)? as usize;
+        let edge_count = read_u32(reader)? as usize;
+
+        let mut nodes = Vec::with_capacity(node_count);
+        for _ in 0..node_count {
+            let first_edge = read_u32(reader)?;
+            let count = read_u32(reader)?;
+            let terminal = read_u32(reader)? != 0;
+            if first_edge as usize + count as usize > edge_count {
+                return Err(invalid_data("edge range out of bounds"));
+            }
+            nodes.push(Node { first_edge, edge_count: count, terminal });
+        }
+
+        let mut edges = Vec::with_capacity(edge_count);
+        for _ in 0..edge_count {
+            let letter = char::from_u32(read_u32(reader)?)
+                .ok_or_else(|| invalid_data("invalid letter"))?;
+            let target = read_u32(reader)?;
+            if target as usize >= node_count {
+                return Err(invalid_data("edge target out of bounds"));
+            }
+            edges.push(Edge { letter, target });
+        }
+
+        if nodes.is_empty() {
+            return Err(invalid_data("graph has no root"));
+        }
+
+        Ok(Dawg { nodes, edges })
+    }
+
+    fn edge_slice(&self, node: NodeId) -> &[Edge] {
+        let node = &self.nodes[node.0 as usize];
+        let start = node.first_edge as usize;
+        &self.edges[start..start + node.edge_count as usize]
+    }
+}
+
+// The GADDAG stores, for every word and every split point, the reversed prefix
+// followed by SEPARATOR and the remaining suffix. Walking it from any letter of
+// a word outwards makes substring lookups and anchor-based generation cheap.
+pub struct Gaddag {
+    graph: Dawg,
+}
+
+impl Gaddag {
+    pub fn from_words<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut entries = Vec::new();
+        for word in words {
+            let letters: Vec<char> = word.as_ref().chars().collect();
+            for split in 1..=letters.len() {
+                let mut entry: String = letters[..split].iter().rev().collect();
+                if split < letters.len() {
+                    entry.push(SEPARATOR);
+                    entry.extend(&letters[split..]);
+                }
+                entries.push(entry);
+            }
+        }
+        entries.sort_unstable();
+        entries.dedup();
+
+        Gaddag { graph: Dawg::from_sorted(entries) }
+    }
+
+    pub fn graph(&self) -> &Dawg {
+        &self.graph
+    }
+
+    // s appears somewhere inside a word iff its reversal is a path from the root.
+    pub fn contains_substring(&self, s: &str) -> bool {
+        let reversed: String = s.chars().rev().collect();
+        self.graph.is_prefix(&reversed)
+    }
+}
+
+// Where the GADDAG for a word list is cached, and the fingerprint of the word
+// list it must have been built from.
+struct CacheInfo {
+    path: PathBuf,
+    fingerprint: u64,
+}
+
+pub struct Lexicon {
+    dawg: Dawg,
+    gaddag: OnceLock<Gaddag>,
+    cache: Option<CacheInfo>,
+}
+
+impl Lexicon {
+    // Reads a word list with one word per line. The GADDAG is cached in
+    // `<filename>.gaddag` and rebuilt whenever the word list changes.
+    pub fn from_file<P>(filename: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let contents = fs::read_to_string(&filename)?;
+        Ok(Lexicon::cached(filename, contents))
+    }
+
+    // Like from_file, but spells each word with the tiles of a tile set, so
+    // that with a CH tile CHICO is four tiles rather than five letters.
+    pub fn from_file_with_tiles<P>(filename: P, tile_set: &TileSet) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let contents = tile_set.encode(&fs::read_to_string(&filename)?.to_uppercase());
+        Ok(Lexicon::cached_as(filename, contents, &encoding_tag(tile_set)))
+    }
+
+    fn cached<P: AsRef<Path>>(filename: P, contents: String) -> Self {
+        Lexicon::cached_as(filename, contents, "")
+    }
+
+    // Word lists spelled with different tile sets get their own caches,
+    // `<filename>.<tag>.gaddag`, so that switching rules back and forth
+    // does not rebuild the GADDAG every time.
+    fn cached_as<P: AsRef<Path>>(filename: P, contents: String, tag: &str) -> Self {
+        let mut path = filename.as_ref().as_os_str().to_owned();
+        path.push(".");
+        if !tag.is_empty() {
+            path.push(tag);
+            path.push(".");
+        }
+        path.push(CACHE_EXTENSION);
+
+        let mut lexicon = Lexicon::from_words(contents.lines());
+        lexicon.cache = Some(CacheInfo {
+            path: PathBuf::from(path),
+            fingerprint: fingerprint(contents.as_bytes()),
+        });
+        lexicon
+    }
+
+    pub fn from_words<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut words: Vec<String> = words
+            .into_iter()
+            .map(|word| word.as_ref().trim().to_uppercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+
+        Lexicon {
+            dawg: Dawg::from_sorted(&words),
+            gaddag: OnceLock::new(),
+            cache: None,
+        }
+    }
+
+    pub fn is_word(&self, word: &str) -> bool {
+        self.dawg.contains(word)
+    }
+
+    pub fn is_prefix(&self, prefix: &str) -> bool {
+        self.dawg.is_prefix(prefix)
+    }
+
+    pub fn next_letters(&self, prefix: &str) -> Vec<char> {
+        self.dawg.next_letters(prefix)
+    }
+
+    pub fn is_substring(&self, s: &str) -> bool {
+        self.gaddag().contains_substring(s)
+    }
+
+    pub fn dawg(&self) -> &Dawg {
+        &self.dawg
+    }
+
+    // The GADDAG is several times larger than the DAWG and slow to build, so it
+    // is only loaded (or built and cached) the first time something asks for it.
+    pub fn gaddag(&self) -> &Gaddag {
+        self.gaddag.get_or_init(|| {
+            if let Some(cache) = &self.cache
+                && let Ok(gaddag) = read_cache(cache)
+            {
+                return gaddag;
+            }
+
+            let gaddag = Gaddag::from_words(self.dawg.words());
+            if let Some(cache) = &self.cache {
+                // a missing cache only costs time, so failing to write one is fine
+                let _ = write_cache(cache, &gaddag);
+            }
+            gaddag
+        })
+    }
+}
+
+fn read_cache(cache: &CacheInfo) -> io::Result<Gaddag> {
+    let mut reader = BufReader::new(File::open(&cache.path)?);
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != CACHE_MAGIC || read_u32(&mut reader)? != CACHE_VERSION {
+        return Err(invalid_data("not a lexicon cache"));
+    }
+    if read_u64(&mut reader)? != cache.fingerprint {
+        return Err(invalid_data("word list has changed"));
+    }
+
+    Ok(Gaddag { graph: Dawg::read_from(&mut reader)? })
+}
+
+// Writes to a temporary file next to the cache and renames it into place, so
+// that an interrupted write or another process reading at the same time never
+// sees half a cache.
+fn write_cache(cache: &CacheInfo, gaddag: &Gaddag) -> io::Result<()> {
+    let mut temp = cache.path.clone().into_os_string();
+    temp.push(format!(".{}.tmp", std::process::id()));
+    let temp = PathBuf::from(temp);
+
+    let result = write_cache_to(&temp, cache.fingerprint, gaddag)
+        .and_then(|()| fs::rename(&temp, &cache.path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_cache_to(path: &Path, fingerprint: u64, gaddag: &Gaddag) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(CACHE_MAGIC)?;
+    write_u32(&mut writer, CACHE_VERSION)?;
+    writer.write_all(&fingerprint.to_le_bytes())?;
+    gaddag.graph.write_to(&mut writer)?;
+    writer.flush()
+}
+
+// Names a tile set's encoding for the cache file name. Sets without tiles of
+// more than one letter spell words letter by letter and share the plain cache.
+fn encoding_tag(tile_set: &TileSet) -> String {
+    let names: String = tile_set
+        .digraphs()
+        .map(|(code, name)| format!("{code}{name};"))
+        .collect();
+    if names.is_empty() {
+        String::new()
+    } else {
+        format!("{:016x}", fingerprint(names.as_bytes()))
+    }
+}
+
+// FNV-1a, which is plenty to notice that the word list was edited.
+fn fingerprint(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn write_u32<W: Write>(writer: &mut W, n: u32) -> io::Result<()> {
+    writer.write_all(&n.to_le_bytes())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct BuildNode {
+    edges: Vec<(char, u32)>,
+    terminal: bool,
+}
+
+// Incremental construction of a minimal acyclic automaton from sorted input
+// (Daciuk et al.). Nodes on the path of the previous word stay unchecked until
+// the next word diverges from them, at which point they are merged with an
+// equivalent registered node or registered themselves.
+struct Builder {
+    nodes: Vec<BuildNode>,
+    free: Vec<u32>,
+    register: HashMap<BuildNode, u32>,
+    unchecked: Vec<(u32, u32)>,
+    previous: Vec<char>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        Builder {
+            nodes: vec![BuildNode::default()],
+            free: Vec::new(),
+            register: HashMap::new(),
+            unchecked: Vec::new(),
+            previous: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, word: &str) {
+        let letters: Vec<char> = word.chars().collect();
+        if letters <= self.previous {
+            // duplicate or out of order, nothing sensible to do with it
+            return;
+        }
+
+        let common = letters
+            .iter()
+            .zip(self.previous.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.minimize(common);
+
+        let mut node = match self.unchecked.last() {
+            Some(&(_, child)) => child,
+            None => 0,
+        };
+        for &letter in &letters[common..] {
+            let child = self.alloc();
+            self.nodes[node as usize].edges.push((letter, child));
+            self.unchecked.push((node, child));
+            node = child;
+        }
+        self.nodes[node as usize].terminal = true;
+        self.previous = letters;
+    }
+
+    fn alloc(&mut self) -> u32 {
+        match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.nodes.push(BuildNode::default());
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn minimize(&mut self, down_to: usize) {
+        while self.unchecked.len() > down_to {
+            let (parent, child) = self.unchecked.pop().expect("Length checked above.");
+            match self.register.get(&self.nodes[child as usize]) {
+                Some(&existing) => {
+                    let edge = self.nodes[parent as usize]
+                        .edges
+                        .last_mut()
+                        .expect("Parent has an edge to child.");
+                    edge.1 = existing;
+                    self.nodes[child as usize] = BuildNode::default();
+                    self.free.push(child);
+                }
+                None => {
+                    self.register.insert(self.nodes[child as usize].clone(), child);
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Dawg {
+        self.minimize(0);
+
+        // renumber the reachable nodes so that the root is 0 and each node's
+        // edges are stored contiguously.
+        let mut new_ids = vec![u32::MAX; self.nodes.len()];
+        let mut order = vec![0u32];
+        new_ids[0] = 0;
+        let mut i = 0;
+        while i < order.len() {
+            let old = order[i] as usize;
+            for &(_, target) in &self.nodes[old].edges {
+                if new_ids[target as usize] == u32::MAX {
+                    new_ids[target as usize] = order.len() as u32;
+                    order.push(target);
+                }
+            }
+            i += 1;
+        }
+
+        let mut nodes = Vec::with_capacity(order.len());
+        let mut edges = Vec::new();
+        for &old in &order {
+            let node = &self.nodes[old as usize];
+            nodes.push(Node {
+                first_edge: edges.len() as u32,
+                edge_count: node.edges.len() as u32,
+                terminal: node.terminal,
+            });
+            for &(letter, target) in &node.edges {
+                edges.push(Edge {
+                    letter,
+                    target: new_ids[target as usize],
+                });
+            }
+        }
+
+        Dawg { nodes, edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_lexicon() -> Lexicon {
+        Lexicon::from_words(["cat", "cats", "car", "cart", "bat", "bats", "at"])
+    }
+
+    #[test]
+    fn test_is_word() {
+        let lexicon = small_lexicon();
+        assert!(lexicon.is_word("CAT"));
+        assert!(!lexicon.is_word("CARTS"));
+        assert!(lexicon.is_word("AT"));
+        assert!(!lexicon.is_word("CA"));
+        assert!(!lexicon.is_word(""));
+    }
+
+    #[test]
+    fn test_prefixes() {
+        let lexicon = small_lexicon();
+        assert!(lexicon.is_prefix("CA"));
+        assert!(lexicon.is_prefix(""));
+        assert!(!lexicon.is_prefix("CT"));
+        assert_eq!(lexicon.next_letters("CA"), vec!['R', 'T']);
+        assert_eq!(lexicon.next_letters("CAT"), vec!['S']);
+        assert!(lexicon.next_letters("Q").is_empty());
+    }
+
+    #[test]
+    fn test_minimized() {
+        // CATS and BATS share every node after the first letter.
+        let dawg = Dawg::from_sorted(["BATS", "CATS"]);
+        assert_eq!(dawg.node_count(), 5);
+        assert_eq!(dawg.words(), vec!["BATS", "CATS"]);
+    }
+
+    #[test]
+    fn test_substrings() {
+        let lexicon = small_lexicon();
+        assert!(lexicon.is_substring("ART"));
+        assert!(lexicon.is_substring("ATS"));
+        assert!(lexicon.is_substring("C"));
+        assert!(!lexicon.is_substring("TAC"));
+        assert!(!lexicon.is_substring("CB"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let lexicon = small_lexicon();
+        let mut bytes = Vec::new();
+        lexicon.gaddag().graph().write_to(&mut bytes).unwrap();
+
+        let graph = Dawg::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(graph.words(), lexicon.gaddag().graph().words());
+        assert!(Dawg::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_cache_rebuilt_when_list_changes() {
+        let dir = std::env::temp_dir().join(format!("words-lexicon-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dict = dir.join("dict.txt");
+        let cache = dir.join("dict.txt.gaddag");
+
+        fs::write(&dict, "CAT\nCATS\n").unwrap();
+        let lexicon = Lexicon::from_file(&dict).unwrap();
+        assert!(lexicon.is_substring("ATS"));
+        assert!(cache.exists());
+
+        // a cache from the old list must not be trusted for the new one
+        fs::write(&dict, "DOG\nDOGS\n").unwrap();
+        let lexicon = Lexicon::from_file(&dict).unwrap();
+        assert!(lexicon.is_substring("OGS"));
+        assert!(!lexicon.is_substring("ATS"));
+
+        // and a fresh lexicon for the same list reads the cache back
+        let lexicon = Lexicon::from_file(&dict).unwrap();
+        assert!(lexicon.is_substring("OGS"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_per_tile_encoding() {
+        let dir = std::env::temp_dir().join(format!("words-encoding-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dict = dir.join("dict.txt");
+        fs::write(&dict, "CHICO\nLLAMA\n").unwrap();
+
+        let spanish = TileSet::spanish();
+        let lexicon = Lexicon::from_file_with_tiles(&dict, &spanish).unwrap();
+        assert!(!lexicon.is_substring("HICO"));
+        let plain = Lexicon::from_file(&dict).unwrap();
+        assert!(plain.is_substring("HICO"));
+
+        // both caches are kept, and neither is overwritten by the other
+        let caches: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".gaddag"))
+            .collect();
+        assert_eq!(caches.len(), 2, "{caches:?}");
+        assert!(caches.contains(&"dict.txt.gaddag".to_string()));
+
+        let lexicon = Lexicon::from_file_with_tiles(&dict, &spanish).unwrap();
+        assert!(lexicon.is_substring(&spanish.encode("ICO")));
+        assert!(!lexicon.is_substring("HICO"));
+
+        // no temporary files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let lexicon = Lexicon::from_file("dict.txt").unwrap();
+        assert!(lexicon.is_word("AARDVARK"));
+        assert!(lexicon.is_word("ZYZZYVA"));
+        assert!(!lexicon.is_word("AARDVAR"));
+        assert!(lexicon.is_prefix("AARDVAR"));
+    }
+}
@@ -1,4 +1,16 @@
+pub mod endgame;
+pub mod equity;
 pub mod game;
+pub mod gcg;
+pub mod layout;
+pub mod lexicon;
 pub mod player;
+pub mod rules;
+pub mod savefile;
+pub mod session;
 pub mod shell;
+pub mod sim;
+pub mod strategy;
+pub mod tileset;
+pub mod tournament;
 pub mod greedy;
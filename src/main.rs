@@ -1,21 +1,44 @@
 use clap::Parser;
+use std::fs::File;
 use std::io;
+use std::path::PathBuf;
 use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+use clap::ValueEnum;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use words::endgame::{EndgameConfig, DEFAULT_NODES};
+use words::equity::LeaveTable;
 use words::game::{Bag, Board};
-use words::player::{TurnResult, Player};
+use words::layout::Layout;
+use words::lexicon::Lexicon;
+use words::player::Player;
+use words::rules::{self, Rules};
+use words::session::Session;
+use words::shell::{HumanShell, RacklessHelper};
+use words::sim::SimConfig;
+use words::strategy::{CpuBot, Strategy};
+use words::tileset::TileSet;
+use words::tournament::{self, Contestant};
 
-#[derive(Debug, Clone, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
 enum PlayerType {
     Human,
     HumanNoRack,
-    CPU,
+    Cpu,
+    Sim,
     None,
 }
 
 // Program to assist in Scrabble-like games.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     // number of human players
     #[arg(value_enum, default_value_t = PlayerType::None)]
     player1: PlayerType,
@@ -28,62 +51,285 @@ struct Args {
 
     #[arg(value_enum, default_value_t = PlayerType::None)]
     player4: PlayerType,
+
+    // resume a game written by the save command. The players come from the file.
+    #[arg(long, value_name = "FILE")]
+    load: Option<PathBuf>,
+
+    // rack leave values for the cpu players.
+    #[arg(long, global = true, value_name = "FILE")]
+    leaves: Option<PathBuf>,
+
+    // plays each sim player looks at.
+    #[arg(long, global = true, default_value_t = SimConfig::default().candidates)]
+    sim_candidates: usize,
+
+    // opponent racks each play is tried against.
+    #[arg(long, global = true, default_value_t = SimConfig::default().iterations)]
+    sim_iterations: usize,
+
+    // turns simulated after each play.
+    #[arg(long, global = true, default_value_t = SimConfig::default().plies)]
+    sim_plies: usize,
+
+    // seconds a sim player may think per turn.
+    #[arg(long, global = true, default_value_t = SimConfig::default().time_budget.as_secs_f64())]
+    sim_time: f64,
+
+    // how many plies deep cpu players search once the bag is empty. To the end
+    // of the game if not given. 0 turns it off.
+    #[arg(long, global = true)]
+    endgame_plies: Option<usize>,
+
+    // positions cpu players look at in the endgame before playing the best
+    // line they have found so far.
+    #[arg(long, global = true, default_value_t = DEFAULT_NODES)]
+    endgame_nodes: usize,
+
+    // the game to play: standard, wwf (Words With Friends), super (21x21) or
+    // spanish. Sets the board layout, letter values, tiles and bingo bonus.
+    #[arg(long, global = true, value_parser = rules::BUILT_IN, default_value = "standard")]
+    rules: String,
+
+    // a built-in board layout (standard, wwf, super) or a layout file.
+    // Defaults to the layout of the rules.
+    #[arg(long, global = true, value_name = "NAME|FILE")]
+    board: Option<String>,
+
+    // a built-in tile set (standard, wwf, super, spanish) or a tile set file, giving
+    // the tiles in the bag and their values. Defaults to the rules' tiles.
+    #[arg(long, global = true, value_name = "NAME|FILE")]
+    tiles: Option<String>,
+
+    // the word list, one word per line.
+    #[arg(long, global = true, value_name = "FILE", default_value = "dict.txt")]
+    dict: PathBuf,
+
+    // play the same game again: the bag and the bots' simulations are seeded
+    // from this. Random if not given.
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    // play bots against each other without printing the games and report
+    // how each did.
+    Tournament {
+        #[arg(value_enum)]
+        player1: PlayerType,
+
+        #[arg(value_enum)]
+        player2: PlayerType,
+
+        #[arg(long, default_value_t = 100)]
+        games: usize,
+
+        // also write the results to this file as CSV.
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
 }
 
 fn main() -> io::Result<()> {
     let args = Args::parse();
+    let load = args.load.clone();
+
+    let leaves = match &args.leaves {
+        Some(path) => match LeaveTable::from_file(path) {
+            Ok(table) => table,
+            Err(e) => {
+                println!("Could not read {}: {e}", path.display());
+                return Err(e);
+            }
+        },
+        None => LeaveTable::default(),
+    };
+    let mut rules = Rules::built_in(&args.rules).expect("Clap only accepts built-in rules.");
+    if let Some(board) = &args.board {
+        match Layout::load(board) {
+            Ok(layout) => rules.layout = Arc::new(layout),
+            Err(e) => {
+                println!("Could not read layout {board}: {e}");
+                return Err(e);
+            }
+        }
+    }
+    if let Some(tiles) = &args.tiles {
+        match TileSet::load(tiles) {
+            Ok(tile_set) => rules.tile_set = tile_set,
+            Err(e) => {
+                println!("Could not read tile set {tiles}: {e}");
+                return Err(e);
+            }
+        }
+    }
+    let rules = Arc::new(rules);
+
+    // spelled with the tiles, so that words with a CH tile can be played
+    let lexicon = match Lexicon::from_file_with_tiles(&args.dict, &rules.tile_set) {
+        Ok(lexicon) => Arc::new(lexicon),
+        Err(e) => {
+            println!("Could not read {}: {e}", args.dict.display());
+            return Err(e);
+        }
+    };
+
+    let endgame = match args.endgame_plies {
+        Some(0) => None,
+        max_plies => Some(EndgameConfig { max_plies, max_nodes: args.endgame_nodes }),
+    };
+    let bots = Bots {
+        cpu: CpuBot { leaves: Arc::new(leaves), endgame, ..CpuBot::default() },
+        sim: SimConfig {
+            candidates: args.sim_candidates,
+            iterations: args.sim_iterations,
+            plies: args.sim_plies,
+            time_budget: Duration::from_secs_f64(args.sim_time.max(0.0)),
+        },
+        seed: args.seed.unwrap_or_else(rand::random),
+    };
+
+    if let Some(Command::Tournament { player1, player2, games, csv }) = args.command {
+        return run_tournament([player1, player2], games, csv, lexicon, rules, &bots);
+    }
 
-    let players = initialize_players(args);
+    let players = initialize_players(args, &bots);
 
-    if players.is_empty() {
+    if players.is_empty() && load.is_none() {
         println!("No players initialized. Please see --help.");
         return Ok(());
     }
 
-    let _ = run(players);
+    let _ = run(players, load, lexicon, rules, &bots);
 
     Ok(())
 }
 
-fn initialize_players(args: Args) -> Vec<Box<Player>> {
-    let player_types = vec![args.player1, args.player2, args.player3, args.player4];
-    let mut players = Vec::new();
-
-    let mut id = 1;
+// The computer players as configured on the command line.
+struct Bots {
+    cpu: CpuBot,
+    sim: SimConfig,
+    seed: u64,
+}
 
-    for player_type in player_types {
-        let new_player = match player_type {
-            PlayerType::Human => Some(Player::new(id, false, false)),
-            PlayerType::HumanNoRack => Some(Player::new(id, true, false)),
-            PlayerType::CPU => Some(Player::new(id, false, true)),
+impl Bots {
+    // seed is for the bot's simulations.
+    fn strategy(&self, player_type: PlayerType, seed: u64) -> Option<Box<dyn Strategy>> {
+        let cpu = CpuBot { rng: StdRng::seed_from_u64(seed), ..self.cpu.clone() };
+        match player_type {
+            PlayerType::Human => Some(Box::new(HumanShell)),
+            PlayerType::HumanNoRack => Some(Box::new(RacklessHelper)),
+            PlayerType::Cpu => Some(Box::new(cpu)),
+            PlayerType::Sim => Some(Box::new(CpuBot { sim: Some(self.sim.clone()), ..cpu })),
             PlayerType::None => None,
-        };
-        
-        if let Some(p) = new_player {
-            players.push(Box::new(p))
         }
+    }
 
-        id += 1;
+    // each player's bot gets its own seed.
+    fn player_seed(&self, id: i32) -> u64 {
+        self.seed.wrapping_add(id as u64)
     }
+}
 
-    players
+fn run_tournament(
+    player_types: [PlayerType; 2],
+    games: usize,
+    csv: Option<PathBuf>,
+    lexicon: Arc<Lexicon>,
+    rules: Arc<Rules>,
+    bots: &Bots,
+) -> io::Result<()> {
+    if !player_types.iter().all(|player_type| matches!(player_type, PlayerType::Cpu | PlayerType::Sim)) {
+        println!("Only cpu and sim players can play in a tournament.");
+        return Ok(());
+    }
+
+    let strategies = player_types.map(|player_type| {
+        move |seed| bots.strategy(player_type, seed).expect("Checked that both players are bots.")
+    });
+    let contestants = [0, 1].map(|i| Contestant {
+        // tell the bots apart when both are of the same type
+        name: format!("{}{}", player_types[i].to_possible_value().expect("No skipped types.").get_name(), i + 1),
+        strategy: &strategies[i],
+    });
+
+    println!("Seed: {}", bots.seed);
+    let stats = tournament::run(lexicon, rules, contestants, games, bots.seed);
+    tournament::write_table(&stats, &mut io::stdout())?;
+
+    if let Some(path) = csv {
+        let written = File::create(&path).and_then(|mut file| tournament::write_csv(&stats, &mut file));
+        if let Err(e) = written {
+            println!("Could not write {}: {e}", path.display());
+            return Err(e);
+        }
+    }
+
+    Ok(())
 }
 
-fn run(mut players: Vec<Box<Player>>) -> io::Result<()> {
-    let mut board = Board::new("dict.txt".to_string(), "partials_dict.txt".to_string());
-    let mut bag = Bag::new();
+fn initialize_players(args: Args, bots: &Bots) -> Vec<Player> {
+    let player_types = vec![args.player1, args.player2, args.player3, args.player4];
+    let mut players = Vec::new();
+
+    for (id, player_type) in (1..).zip(player_types) {
+        if let Some(strategy) = bots.strategy(player_type, bots.player_seed(id)) {
+            players.push(Player::new(id, strategy))
+        }
+    }
 
-    while !bag.is_empty() {
-        for player in &mut players {
-            let result = player.play_turn(&mut board, &mut bag);
-            if matches!(result, TurnResult::Exit) {
-                exit(0);
+    players
+}
+
+fn run(
+    players: Vec<Player>,
+    load: Option<PathBuf>,
+    lexicon: Arc<Lexicon>,
+    rules: Arc<Rules>,
+    bots: &Bots,
+) -> io::Result<()> {
+    let mut session = match load {
+        Some(path) => match Session::load(&path, lexicon, rules, bots.seed) {
+            Ok(session) => session,
+            Err(e) => {
+                println!("Could not load {}: {e}", path.display());
+                return Err(e);
             }
+        },
+        None => {
+            let bag = Bag::with_rules(&rules, bots.seed);
+            Session::new(Board::with_rules(lexicon, rules), bag, players)
+        }
+    };
+
+    // loaded players get the bots configured on the command line
+    for player in &mut session.players {
+        let player_type = PlayerType::from_str(player.strategy.name(), true).ok();
+        let seed = bots.player_seed(player.id);
+        if let Some(strategy) = player_type.and_then(|player_type| bots.strategy(player_type, seed)) {
+            player.strategy = strategy;
         }
     }
 
+    let standings = match session.run() {
+        Some(standings) => standings,
+        None => exit(0),
+    };
+
     println!("Game finished!");
-    board.show();
+    session.board.show();
+
+    for standing in &standings.standings {
+        println!(
+            "Player {}: {} ({:+} for racks)",
+            standing.id, standing.score, standing.rack_adjustment
+        );
+    }
+    match standings.winner {
+        Some(id) => println!("Player {id} wins!"),
+        None => println!("The game is a tie."),
+    }
 
     Ok(())
 }
@@ -1,81 +1,173 @@
 use crate::{
-    game::{Bag, Board, Rack},
-    greedy::find_greediest_word,
-    shell::{Shell, ShellStatus},
+    game::{rack_for, Bag, Board, Placement, Rack},
+    strategy::{Command, GameView, Move, Strategy, Turn},
 };
 
 pub enum TurnResult {
     Score(i32),
-    Swap,
-    Exit,
+    // what the withdrawn play would have scored.
+    Withdrawn { play: Placement, score: i32 },
+    // the tiles that went back into the bag.
+    Swap(Vec<char>),
+    Pass,
+    // the player gave the session a command. Their turn is not over.
+    Command(Command),
 }
 
+// Exchanges are only allowed while the bag can refill a whole rack.
+pub const MIN_BAG_FOR_SWAP: usize = 7;
+
 pub struct Player {
     pub rack: Rack,
     pub id: i32,
     pub score: i32,
-    pub rackless: bool,
-    pub cpu: bool,
+    // decides what the player does each turn.
+    pub strategy: Box<dyn Strategy>,
+    // don't print the turns, e.g. when bots play many games.
+    pub quiet: bool,
+    // the short and full names from an imported game record, kept so that
+    // the record can be written out again with them.
+    pub nickname: Option<String>,
+    pub full_name: Option<String>,
 }
 
 impl Player {
-    pub fn new(id: i32, rackless: bool, cpu: bool) -> Self {
-        if rackless && cpu {
-            panic!("CPU cannot be rackless.");
-        }
-
+    pub fn new(id: i32, strategy: Box<dyn Strategy>) -> Self {
         Player {
             rack: Rack::new(),
             id,
             score: 0,
-            rackless,
-            cpu,
+            strategy,
+            quiet: false,
+            nickname: None,
+            full_name: None,
         }
     }
 
-    pub fn play_turn(&mut self, board: &mut Board, bag: &mut Bag) -> TurnResult {
-        println!("Player {}'s turn.", self.id);
-        if !self.rackless {
-            self.rack.draw(bag);
-        }
+    pub fn rackless(&self) -> bool {
+        self.strategy.rackless()
+    }
 
-        let turn_result;
-        if self.cpu {
-            turn_result = self.play_turn_cpu(board, bag);
-        } else {
-            turn_result = self.play_turn_player(board, bag);
+    // a player has gone out once the bag is empty and they have played every tile.
+    pub fn is_out(&self, bag: &Bag) -> bool {
+        !self.rackless() && bag.is_empty() && self.rack.is_empty()
+    }
+
+    // opponents is how many other players are in the game and scoreless_turns
+    // how many turns in a row have gone without points.
+    pub fn play_turn(&mut self, board: &mut Board, bag: &mut Bag, opponents: usize, scoreless_turns: u32) -> TurnResult {
+        if !self.quiet {
+            println!("Player {}'s turn.", self.id);
         }
 
+        // a person is asked again after a move that cannot be made, but a bot
+        // that chooses one has a bug
+        let turn_result = loop {
+            let view = GameView { board, rack: &self.rack, bag_size: bag.size(), opponents, scoreless_turns };
+            let made = match self.strategy.choose(&view) {
+                Turn::Move(chosen) => self.make_move(board, bag, chosen),
+                Turn::Withdrawn { word, row, col, across } => self.withdraw(board, word, row, col, across),
+                Turn::Command(command) => return TurnResult::Command(command),
+            };
+            match made {
+                Ok(turn_result) => break turn_result,
+                Err(e) if self.strategy.interactive() => println!("{e}"),
+                Err(e) => panic!("Player {} ({}) chose a move it cannot make: {e}", self.id, self.strategy.name()),
+            }
+        };
+
         let score_delta = match turn_result {
             TurnResult::Score(n) => n,
-            TurnResult::Swap => 0,
-            TurnResult::Exit => return TurnResult::Exit,
+            _ => 0,
         };
 
         self.score += score_delta;
-        println!("Score: {}\n", self.score);
-        TurnResult::Score(score_delta)
+        if !self.rackless() {
+            self.rack.draw(bag);
+        }
+        if !self.quiet {
+            println!("Score: {}\n", self.score);
+        }
+        turn_result
     }
 
-    fn play_turn_cpu(&mut self, board: &mut Board, _bag: &mut Bag) -> TurnResult {
-        let word_choice = find_greediest_word(board, &mut self.rack);
-        if word_choice.across {
-            board.write_across_from_rack(&mut self.rack, word_choice.word, word_choice.row, word_choice.col);
+    // Strategies only see the game, so their moves are checked here. A move
+    // that cannot be made leaves the game as it was.
+    fn make_move(&mut self, board: &mut Board, bag: &mut Bag, chosen: Move) -> Result<TurnResult, String> {
+        let (word, row, col, across) = match chosen {
+            Move::Play { word, row, col, across } => (word, row, col, across),
+            Move::Exchange(tiles) => {
+                if self.rackless() {
+                    return Err(String::from("Players without a rack cannot exchange."));
+                }
+                // exchanges need a bag with at least a rack's worth of tiles left
+                if bag.size() < MIN_BAG_FOR_SWAP {
+                    return Err(format!(
+                        "The bag has {} tiles left and exchanges need at least {MIN_BAG_FOR_SWAP}.",
+                        bag.size()
+                    ));
+                }
+                if !self.rack.swap(bag, tiles.clone()) {
+                    return Err(String::from("Cannot exchange tiles that are not on the rack."));
+                }
+                if !self.quiet {
+                    println!("Player {} exchanges {} tiles.", self.id, tiles.len());
+                }
+                return Ok(TurnResult::Swap(tiles));
+            }
+            Move::Pass => {
+                if !self.quiet {
+                    println!("Player {} passes.", self.id);
+                }
+                return Ok(TurnResult::Pass);
+            }
+        };
+
+        // players without a rack play whatever tiles they say they played
+        let mut own_rack = rack_for(&word);
+        let rack = if self.rackless() { &mut own_rack } else { &mut self.rack };
+        let placed = if across {
+            board.write_across_from_rack(rack, word, row, col)
         } else {
-            board.write_down_from_rack(&mut self.rack, word_choice.word, word_choice.row, word_choice.col);
-        }
-        let score = board.submit();
+            board.write_down_from_rack(rack, word, row, col)
+        };
 
-        TurnResult::Score(score)
+        match placed.and_then(|()| board.submit()) {
+            Ok(score) => {
+                if !self.quiet {
+                    println!("Play is worth {} points.", score);
+                }
+                Ok(TurnResult::Score(score))
+            }
+            Err(e) => {
+                board.unstage_to_rack(rack);
+                Err(format!("Cannot play that: {e}"))
+            }
+        }
     }
 
-    fn play_turn_player(&mut self, board: &mut Board, bag: &mut Bag) -> TurnResult {
-        let mut shell = Shell::new(bag, board, self);
-        match shell.main_loop() {
-            ShellStatus::Exit => TurnResult::Exit,
-            ShellStatus::Submit(score) => TurnResult::Score(score),
-            ShellStatus::Swap => TurnResult::Swap,
-            _ => TurnResult::Exit, // should not be possible
+    // The play goes on the board only to be scored, since the game record
+    // shows what it would have scored before it was taken back.
+    fn withdraw(&self, board: &mut Board, word: String, row: usize, col: usize, across: bool) -> Result<TurnResult, String> {
+        let mut rack = rack_for(&word);
+        let placed = if across {
+            board.write_across_from_rack(&mut rack, word, row, col)
+        } else {
+            board.write_down_from_rack(&mut rack, word, row, col)
+        };
+        let play = board.staged_placement();
+        let score = board.score();
+        board.unstage();
+        if let Err(e) = placed {
+            return Err(format!("Cannot play that: {e}"));
+        }
+        let Some(play) = play else {
+            return Err(String::from("Nothing was played."));
+        };
+
+        if !self.quiet {
+            println!("Player {}'s play comes off the board.", self.id);
         }
+        Ok(TurnResult::Withdrawn { play, score })
     }
 }
@@ -0,0 +1,108 @@
+// Rule sets: everything that differs between Scrabble-likes apart from the
+// word list. A rule set picks the board layout, the tile set (which tiles go
+// in the bag and what each letter is worth) and the bonus for playing a whole
+// rack.
+
+use std::sync::Arc;
+
+use crate::layout::Layout;
+use crate::tileset::TileSet;
+
+// the rule sets that can be chosen by name.
+pub const BUILT_IN: [&str; 4] = ["standard", "wwf", "super", "spanish"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rules {
+    // written to saved games so they are loaded with the same rules.
+    pub name: String,
+    pub layout: Arc<Layout>,
+    pub tile_set: TileSet,
+    // extra points for playing every tile on a full rack.
+    pub bingo_bonus: i32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::standard()
+    }
+}
+
+impl Rules {
+    pub fn standard() -> Self {
+        Rules {
+            name: String::from("standard"),
+            layout: Arc::new(Layout::standard()),
+            tile_set: TileSet::standard(),
+            bingo_bonus: 50,
+        }
+    }
+
+    // the 21x21 game, with twice as many tiles and the standard letter values.
+    pub fn super_sized() -> Self {
+        Rules {
+            name: String::from("super"),
+            layout: Arc::new(Layout::super_sized()),
+            tile_set: TileSet::super_sized(),
+            bingo_bonus: 50,
+        }
+    }
+
+    // the standard board with Spanish tiles, including the CH, LL and RR
+    // digraphs. Needs a Spanish word list.
+    pub fn spanish() -> Self {
+        Rules {
+            name: String::from("spanish"),
+            layout: Arc::new(Layout::standard()),
+            tile_set: TileSet::spanish(),
+            bingo_bonus: 50,
+        }
+    }
+
+    // Words With Friends.
+    pub fn wwf() -> Self {
+        Rules {
+            name: String::from("wwf"),
+            layout: Arc::new(Layout::wwf()),
+            tile_set: TileSet::wwf(),
+            bingo_bonus: 35,
+        }
+    }
+
+    // a built-in rule set by name, see BUILT_IN.
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Rules::standard()),
+            "wwf" => Some(Rules::wwf()),
+            "super" => Some(Rules::super_sized()),
+            "spanish" => Some(Rules::spanish()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in() {
+        let standard = Rules::standard();
+        assert_eq!(standard.tile_set.tiles().len(), 100);
+        assert_eq!(standard.layout.size(), 15);
+
+        let wwf = Rules::built_in("wwf").unwrap();
+        assert_eq!(wwf.tile_set.tiles().len(), 104);
+        assert_eq!(wwf.tile_set.score_letter('B'), 4);
+        assert_eq!(wwf.bingo_bonus, 35);
+        assert_eq!(wwf.layout.size(), 15);
+
+        let super_sized = Rules::built_in("super").unwrap();
+        assert_eq!(super_sized.tile_set.tiles().len(), 200);
+        assert_eq!(super_sized.layout.size(), 21);
+
+        let spanish = Rules::built_in("spanish").unwrap();
+        assert_eq!(spanish.tile_set.tiles().len(), 100);
+        assert_eq!(spanish.tile_set.name(spanish.tile_set.encode("CH").chars().next().unwrap()), "CH");
+        assert!(Rules::built_in("nope").is_none());
+    }
+}
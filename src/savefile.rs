@@ -0,0 +1,417 @@
+// Reads and writes saved games. The format is plain text, one item per line:
+//
+//   words-save 1
+//   turn 1
+//   scoreless 0
+//   bag EIT*QA...
+//   player 1 human 24 AEHNRST
+//   player 2 cpu 0 -
+//   rules standard     (the rule set's name)
+//   layout
+//   start 7 7          (the board layout, as read by Layout::parse)
+//   tw -- -- dl ...
+//   tiles
+//   A 9 1              (the tile set, as read by TileSet::parse)
+//   ...
+//   board
+//   ...............    (one row per line, '.' is empty, lowercase is a blank)
+//   history
+//   1 ABDEKLY 24 24 play 7 5 across BLEAK
+//   2 QVVWWXZ 0 0 exchange QVVWW
+//   1 AEHNRST 0 24 pass
+//   2 V -4 -4 endrack V
+//
+// Games imported from other programs may also have "withdrawn" for a play
+// challenged off the board, and "challenge" and "time" for points won in a
+// challenge or lost on time.
+//
+// Racks are written as their tiles with '*' for blanks, or '-' when empty.
+// History lines give the player, their rack before the turn, the points
+// scored, their total afterwards and what they did. Files without rules, a
+// layout or a tile set are played with the ones the caller asks for.
+
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+
+use crate::game::{blank_for, Bag, Board, Rack};
+use crate::layout::Layout;
+use crate::lexicon::Lexicon;
+use crate::player::Player;
+use crate::rules::Rules;
+use crate::session::{Action, HistoryEntry, Session};
+use crate::shell::{HumanShell, RacklessHelper};
+use crate::sim::SimConfig;
+use crate::strategy::{CpuBot, Strategy};
+use crate::tileset::TileSet;
+
+const HEADER: &str = "words-save 1";
+
+pub fn write<W: Write>(session: &Session, writer: &mut W) -> io::Result<()> {
+    let tile_set = &session.board.rules().tile_set;
+    writeln!(writer, "{HEADER}")?;
+    writeln!(writer, "turn {}", session.turn)?;
+    writeln!(writer, "scoreless {}", session.scoreless_turns)?;
+    writeln!(writer, "bag {}", format_rack(&session.bag.tiles, tile_set))?;
+
+    for player in &session.players {
+        writeln!(
+            writer,
+            "player {} {} {} {}",
+            player.id, player.strategy.name(), player.score, format_rack(&player.rack.get_tiles_vec(), tile_set)
+        )?;
+    }
+
+    let board = &session.board;
+    writeln!(writer, "rules {}", board.rules().name)?;
+    writeln!(writer, "layout")?;
+    write!(writer, "{}", board.layout())?;
+    writeln!(writer, "tiles")?;
+    write!(writer, "{}", board.rules().tile_set)?;
+    writeln!(writer, "board")?;
+    for row in 0..board.size() {
+        let line: String = (0..board.size())
+            .map(|col| match board.get_tile(row, col) {
+                '-' => String::from("."),
+                tile if board.is_blank(row, col) => tile_set.name(blank_for(tile)),
+                tile => tile_set.name(tile),
+            })
+            .collect();
+        writeln!(writer, "{line}")?;
+    }
+
+    writeln!(writer, "history")?;
+    for entry in &session.history {
+        write!(writer, "{} {} {} {} ", entry.player, format_rack(&entry.rack, tile_set), entry.score, entry.total)?;
+        match &entry.action {
+            Action::Play { word, row, col, across } => {
+                let direction = if *across { "across" } else { "down" };
+                writeln!(writer, "play {row} {col} {direction} {}", tile_set.decode(word))?;
+            }
+            Action::Exchange(tiles) => writeln!(writer, "exchange {}", format_rack(tiles, tile_set))?,
+            Action::Pass => writeln!(writer, "pass")?,
+            Action::EndRack(tiles) => writeln!(writer, "endrack {}", format_rack(tiles, tile_set))?,
+            Action::Withdrawn => writeln!(writer, "withdrawn")?,
+            Action::ChallengeBonus => writeln!(writer, "challenge")?,
+            Action::TimePenalty => writeln!(writer, "time")?,
+        }
+    }
+
+    writer.flush()
+}
+
+pub fn read<R: BufRead>(reader: &mut R, lexicon: Arc<Lexicon>, rules: Arc<Rules>) -> io::Result<Session> {
+    let mut lines = reader.lines();
+    let mut next_line = || -> io::Result<String> {
+        lines.next().unwrap_or_else(|| Err(invalid_data("unexpected end of file")))
+    };
+
+    if next_line()?.trim() != HEADER {
+        return Err(invalid_data("not a saved game"));
+    }
+
+    let turn = parse_number(field(&next_line()?, "turn")?)?;
+    let scoreless_turns = parse_number(field(&next_line()?, "scoreless")?)?;
+
+    // the tiles are read once the tile set is known
+    let bag = field(&next_line()?, "bag")?.to_string();
+    let mut player_lines = Vec::new();
+    let mut line = next_line()?;
+    while let Ok(rest) = field(&line, "player") {
+        player_lines.push(rest.to_string());
+        line = next_line()?;
+    }
+    if player_lines.is_empty() || turn >= player_lines.len() {
+        return Err(invalid_data("bad player list"));
+    }
+
+    let mut rules = rules;
+    if let Ok(name) = field(&line, "rules") {
+        let named = Rules::built_in(name).ok_or_else(|| invalid_data(&format!("unknown rules '{name}'")))?;
+        rules = Arc::new(named);
+        line = next_line()?;
+    }
+    if line.trim() == "layout" {
+        let (text, next) = read_block(&mut next_line)?;
+        Arc::make_mut(&mut rules).layout = Arc::new(Layout::parse(&text)?);
+        line = next;
+    }
+    if line.trim() == "tiles" {
+        let (text, next) = read_block(&mut next_line)?;
+        Arc::make_mut(&mut rules).tile_set = TileSet::parse(&text)?;
+        line = next;
+    }
+
+    if line.trim() != "board" {
+        return Err(invalid_data("expected board"));
+    }
+    let tile_set = &rules.tile_set;
+    let bag = Bag::from_tiles(parse_rack(&bag, tile_set)?);
+    let players = player_lines
+        .iter()
+        .map(|line| parse_player(line, tile_set))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut board = Board::with_rules(lexicon, rules.clone());
+    let mut tiles = Vec::new();
+    for row in 0..board.size() {
+        let line = rules.tile_set.encode(next_line()?.trim());
+        if line.chars().count() != board.size() {
+            return Err(invalid_data(&format!("board row {row} has the wrong length")));
+        }
+        for (col, tile) in line.chars().enumerate() {
+            match tile {
+                '.' => {}
+                tile if tile.is_alphabetic() => tiles.push((row, col, tile)),
+                _ => return Err(invalid_data(&format!("bad tile '{tile}' on the board"))),
+            }
+        }
+    }
+    board.restore_tiles(&tiles).map_err(|e| invalid_data(&e.to_string()))?;
+
+    if next_line()?.trim() != "history" {
+        return Err(invalid_data("expected history"));
+    }
+    let mut history = Vec::new();
+    for line in lines {
+        let line = line?;
+        if !line.trim().is_empty() {
+            history.push(parse_history(&line, &rules.tile_set)?);
+        }
+    }
+
+    Ok(Session { board, bag, players, turn, scoreless_turns, history, undo: Vec::new(), redo: Vec::new() })
+}
+
+// Reads the lines of a layout or tile set up to the keyword that starts the
+// next block, which is returned with them.
+fn read_block(next_line: &mut impl FnMut() -> io::Result<String>) -> io::Result<(String, String)> {
+    let mut text = String::new();
+    loop {
+        let line = next_line()?;
+        if matches!(line.trim(), "tiles" | "board") {
+            return Ok((text, line));
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+}
+
+fn parse_player(line: &str, tile_set: &TileSet) -> io::Result<Player> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [id, kind, score, rack] = fields[..] else {
+        return Err(invalid_data("player lines need an id, type, score and rack"));
+    };
+
+    let strategy: Box<dyn Strategy> = match kind {
+        "human" => Box::new(HumanShell),
+        "human-no-rack" => Box::new(RacklessHelper),
+        "cpu" => Box::new(CpuBot::default()),
+        "sim" => Box::new(CpuBot { sim: Some(SimConfig::default()), ..CpuBot::default() }),
+        _ => return Err(invalid_data(&format!("unknown player type '{kind}'"))),
+    };
+    let mut player = Player::new(parse_number(id)?, strategy);
+    player.score = parse_number(score)?;
+
+    let mut tiles = Rack::new();
+    for tile in parse_rack(rack, tile_set)? {
+        tiles.add_tile(tile);
+    }
+    player.rack = tiles;
+
+    Ok(player)
+}
+
+fn parse_history(line: &str, tile_set: &TileSet) -> io::Result<HistoryEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 5 {
+        return Err(invalid_data("history line is too short"));
+    }
+
+    let action = match fields[4..] {
+        ["play", row, col, direction, word] => Action::Play {
+            word: tile_set.encode(word),
+            row: parse_number(row)?,
+            col: parse_number(col)?,
+            across: match direction {
+                "across" => true,
+                "down" => false,
+                _ => return Err(invalid_data(&format!("unknown direction '{direction}'"))),
+            },
+        },
+        ["exchange", tiles] => Action::Exchange(parse_rack(tiles, tile_set)?),
+        ["pass"] => Action::Pass,
+        ["endrack", tiles] => Action::EndRack(parse_rack(tiles, tile_set)?),
+        ["withdrawn"] => Action::Withdrawn,
+        ["challenge"] => Action::ChallengeBonus,
+        ["time"] => Action::TimePenalty,
+        _ => return Err(invalid_data(&format!("bad history line '{line}'"))),
+    };
+
+    Ok(HistoryEntry {
+        player: parse_number(fields[0])?,
+        rack: parse_rack(fields[1], tile_set)?,
+        action,
+        score: parse_number(fields[2])?,
+        total: parse_number(fields[3])?,
+    })
+}
+
+// the rest of the line after the keyword.
+fn field<'a>(line: &'a str, keyword: &str) -> io::Result<&'a str> {
+    match line.trim().split_once(' ') {
+        Some((word, rest)) if word == keyword => Ok(rest.trim()),
+        _ => Err(invalid_data(&format!("expected {keyword}"))),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> io::Result<T> {
+    s.trim().parse().map_err(|_| invalid_data(&format!("'{s}' is not a number")))
+}
+
+fn format_rack(tiles: &[char], tile_set: &TileSet) -> String {
+    if tiles.is_empty() {
+        String::from("-")
+    } else {
+        tiles.iter().map(|&tile| tile_set.name(tile)).collect()
+    }
+}
+
+fn parse_rack(s: &str, tile_set: &TileSet) -> io::Result<Vec<char>> {
+    if s == "-" {
+        return Ok(Vec::new());
+    }
+    tile_set
+        .encode(s)
+        .chars()
+        .map(|tile| match tile {
+            '*' => Ok(tile),
+            tile if tile.is_uppercase() => Ok(tile),
+            _ => Err(invalid_data(&format!("bad tile '{tile}'"))),
+        })
+        .collect()
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(session: &Session) -> Session {
+        let mut bytes = Vec::new();
+        write(session, &mut bytes).unwrap();
+        read(&mut bytes.as_slice(), session.board.lexicon().clone(), Arc::new(Rules::standard())).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let players = vec![Player::new(1, Box::new(CpuBot::default())), Player::new(2, Box::new(RacklessHelper))];
+        let mut session = Session::new(Board::new("dict.txt".to_string()), Bag::new(), players);
+        session.play_turn();
+
+        let loaded = round_trip(&session);
+        assert_eq!(loaded.turn, 1);
+        assert_eq!(loaded.scoreless_turns, session.scoreless_turns);
+        assert_eq!(loaded.bag.tiles, session.bag.tiles);
+        assert_eq!(loaded.history, session.history);
+        for (loaded, player) in loaded.players.iter().zip(&session.players) {
+            assert_eq!(loaded.id, player.id);
+            assert_eq!(loaded.score, player.score);
+            assert_eq!(loaded.strategy.name(), player.strategy.name());
+            assert_eq!(loaded.rackless(), player.rackless());
+            assert_eq!(loaded.rack.get_tiles_vec(), player.rack.get_tiles_vec());
+        }
+        assert_eq!(loaded.board.get_board_chars(), session.board.get_board_chars());
+        let mut neighbors = session.board.get_neighbors();
+        let mut loaded_neighbors = loaded.board.get_neighbors();
+        neighbors.sort();
+        loaded_neighbors.sort();
+        assert_eq!(loaded_neighbors, neighbors);
+        for row in 0..15 {
+            for col in 0..15 {
+                assert_eq!(loaded.board.get_val(row, col), session.board.get_val(row, col));
+                assert_eq!(loaded.board.is_blank(row, col), session.board.is_blank(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_blanks_keep_their_value() {
+        let mut session = Session::new(Board::new("dict.txt".to_string()), Bag::new(), vec![Player::new(1, Box::new(RacklessHelper))]);
+        session.board.restore_tiles(&[(7, 7, 'q'), (7, 8, 'I')]).unwrap();
+
+        let loaded = round_trip(&session);
+        assert!(loaded.board.is_blank(7, 7));
+        assert_eq!(loaded.board.get_val(7, 7), 0);
+        assert_eq!(loaded.board.get_val(7, 8), 1);
+    }
+
+    #[test]
+    fn test_bad_file() {
+        let lexicon = Arc::new(Lexicon::from_words(["QI"]));
+        let rules = Arc::new(Rules::standard());
+        assert!(read(&mut "not a save\n".as_bytes(), lexicon.clone(), rules.clone()).is_err());
+        assert!(read(&mut "words-save 1\nturn 0\n".as_bytes(), lexicon, rules).is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = Arc::new(Layout::parse("tw -- --\n-- dw --\n-- -- tl\n").unwrap());
+        let rules = Rules { layout: layout.clone(), ..Rules::standard() };
+        let board = Board::with_rules(Arc::new(Lexicon::from_words(["QI"])), Arc::new(rules));
+        let mut session = Session::new(board, Bag::from_tiles(Vec::new()), vec![Player::new(1, Box::new(RacklessHelper))]);
+        session.board.restore_tiles(&[(1, 1, 'Q'), (1, 2, 'I')]).unwrap();
+
+        let loaded = round_trip(&session);
+        assert_eq!(loaded.board.layout(), &layout);
+        assert_eq!(loaded.board.get_board_chars(), session.board.get_board_chars());
+    }
+
+    #[test]
+    fn test_rules() {
+        let board = Board::with_rules(Arc::new(Lexicon::from_words(["QI"])), Arc::new(Rules::wwf()));
+        let mut session = Session::new(board, Bag::from_tiles(Vec::new()), vec![Player::new(1, Box::new(RacklessHelper))]);
+        session.board.restore_tiles(&[(7, 7, 'Q'), (7, 8, 'I')]).unwrap();
+
+        let loaded = round_trip(&session);
+        assert_eq!(loaded.board.rules().as_ref(), &Rules::wwf());
+        assert_eq!(loaded.board.get_val(7, 7), 10);
+    }
+
+    #[test]
+    fn test_tile_set() {
+        let tile_set = TileSet::parse("Q 3 2\nI 4 1\n").unwrap();
+        let rules = Rules { tile_set: tile_set.clone(), ..Rules::standard() };
+        let board = Board::with_rules(Arc::new(Lexicon::from_words(["QI"])), Arc::new(rules));
+        let mut session = Session::new(board, Bag::from_tiles(Vec::new()), vec![Player::new(1, Box::new(RacklessHelper))]);
+        session.board.restore_tiles(&[(7, 7, 'Q'), (7, 8, 'I')]).unwrap();
+
+        let loaded = round_trip(&session);
+        assert_eq!(loaded.board.rules().tile_set, tile_set);
+        assert_eq!(loaded.board.get_val(7, 7), 2);
+    }
+
+    #[test]
+    fn test_digraphs() {
+        let rules = Rules::spanish();
+        let word = rules.tile_set.encode("CHILLAR");
+        let lexicon = Arc::new(Lexicon::from_words([word.as_str()]));
+        let board = Board::with_rules(lexicon, Arc::new(rules.clone()));
+        let mut session = Session::new(board, Bag::from_tiles(Vec::new()), vec![Player::new(1, Box::new(RacklessHelper))]);
+        let ch = word.chars().next().unwrap();
+        // a blank standing in for LL
+        let ll = rules.tile_set.encode("ll").chars().next().unwrap();
+        session.board.restore_tiles(&[(7, 7, ch), (7, 8, 'I'), (7, 9, ll)]).unwrap();
+        session.players[0].rack.add_tile(ch);
+        session.players[0].rack.add_tile('*');
+
+        let loaded = round_trip(&session);
+        assert_eq!(loaded.board.rules().name, "spanish");
+        assert_eq!(loaded.board.get_tile(7, 7), ch);
+        assert!(loaded.board.is_blank(7, 9));
+        assert_eq!(loaded.board.get_tile(7, 9), ll.to_uppercase().next().unwrap());
+        assert_eq!(loaded.players[0].rack.get_tiles_vec(), vec![ch, '*']);
+    }
+}
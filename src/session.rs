@@ -0,0 +1,662 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::game::{Bag, Board, Rack};
+use crate::gcg;
+use crate::lexicon::Lexicon;
+use crate::player::{Player, TurnResult};
+use crate::rules::Rules;
+use crate::savefile;
+use crate::strategy::Command;
+
+// The game ends after this many turns in a row without points.
+pub const MAX_SCORELESS_TURNS: u32 = 6;
+
+pub struct Session {
+    pub board: Board,
+    pub bag: Bag,
+    pub players: Vec<Player>,
+    // index into players of whose turn it is.
+    pub turn: usize,
+    pub scoreless_turns: u32,
+    pub history: Vec<HistoryEntry>,
+    // what the turns played since the game was started or loaded changed, so
+    // they can be undone. The last one goes with the last history entry.
+    pub(crate) undo: Vec<TurnChanges>,
+    // turns that were undone, the most recent last, to be redone.
+    pub(crate) redo: Vec<(HistoryEntry, TurnChanges)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    // the whole main word, with blanks in lowercase.
+    Play { word: String, row: usize, col: usize, across: bool },
+    Exchange(Vec<char>),
+    Pass,
+    // tiles left on the racks when the game ended. A player who went out
+    // scores the tiles on everyone else's rack.
+    EndRack(Vec<char>),
+    // the player's last play was challenged off the board, e.g. in an
+    // imported game record. Scores minus what the play scored.
+    Withdrawn,
+    // points for a play that stood after a challenge.
+    ChallengeBonus,
+    // points lost for going over time.
+    TimePenalty,
+}
+
+impl Action {
+    // Passes and exchanges count towards ending the game on scoreless turns,
+    // and so does a play challenged off the board, which is a pass. A play
+    // keeps the game going even if it scores nothing, e.g. one of blanks.
+    pub fn is_scoreless(&self) -> bool {
+        matches!(self, Action::Exchange(_) | Action::Pass | Action::Withdrawn)
+    }
+
+    // Score adjustments change a total between turns without being a turn.
+    pub fn is_adjustment(&self) -> bool {
+        matches!(self, Action::ChallengeBonus | Action::TimePenalty)
+    }
+}
+
+// One finished turn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub player: i32,
+    // the rack at the start of the turn. Empty for players without a rack.
+    pub rack: Vec<char>,
+    pub action: Action,
+    pub score: i32,
+    // the player's score after the turn.
+    pub total: i32,
+}
+
+// What a turn did besides what its history entry says.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurnChanges {
+    // the tiles put on the board, with blanks in lowercase.
+    pub placed: Vec<(usize, usize, char)>,
+    // The bag on the other side of the turn, so that undo and redo put back
+    // its tiles in the same order and it shuffles the same way afterwards.
+    // The bag from before the turn until it is undone, and from after it
+    // until it is redone.
+    pub bag: Bag,
+    // the rack at the end of the turn, including the tiles drawn.
+    pub rack_after: Vec<char>,
+    // scoreless turns in a row before this one.
+    pub scoreless_turns: u32,
+}
+
+pub struct Standing {
+    pub id: i32,
+    pub score: i32,
+    // points gained or lost for the tiles left on the racks.
+    pub rack_adjustment: i32,
+}
+
+pub struct Standings {
+    pub standings: Vec<Standing>,
+    // None if the game was tied.
+    pub winner: Option<i32>,
+}
+
+impl Session {
+    pub fn new(board: Board, mut bag: Bag, mut players: Vec<Player>) -> Self {
+        for player in &mut players {
+            if !player.rackless() {
+                player.rack.draw(&mut bag);
+            }
+        }
+
+        Session {
+            board,
+            bag,
+            players,
+            turn: 0,
+            scoreless_turns: 0,
+            history: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    // Files ending in .gcg are game records, anything else is a saved game.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let is_gcg = is_gcg(path.as_ref());
+        let mut writer = BufWriter::new(File::create(path)?);
+        if is_gcg {
+            gcg::write(self, &mut writer)
+        } else {
+            savefile::write(self, &mut writer)
+        }
+    }
+
+    // Saved games bring their own rules and layout. Game records are replayed
+    // with the rules given.
+    // seed shuffles the bag of an imported game record, which does not say
+    // what order the tiles left in the bag were in.
+    pub fn load<P: AsRef<Path>>(path: P, lexicon: Arc<Lexicon>, rules: Arc<Rules>, seed: u64) -> io::Result<Self> {
+        let is_gcg = is_gcg(path.as_ref());
+        let mut reader = BufReader::new(File::open(path)?);
+        if is_gcg {
+            gcg::read(&mut reader, lexicon, rules, seed)
+        } else {
+            savefile::read(&mut reader, lexicon, rules)
+        }
+    }
+
+    // A game loaded mid-session keeps the players it already has, e.g. bots
+    // set up with --leaves or --seed, rather than the defaults a saved game
+    // names. Players are matched by id and kept only if they are still the
+    // same kind of player.
+    fn keep_strategies(&mut self, current: &mut [Player]) {
+        for player in &mut self.players {
+            if let Some(old) = current.iter_mut().find(|old| old.id == player.id)
+                && old.strategy.name() == player.strategy.name()
+            {
+                std::mem::swap(&mut player.strategy, &mut old.strategy);
+                player.quiet = old.quiet;
+            }
+        }
+    }
+
+    // plays turns until the game is over. Returns None if a player asked to exit.
+    pub fn run(&mut self) -> Option<Standings> {
+        while !self.is_over() {
+            if matches!(self.play_turn(), TurnResult::Command(Command::Exit)) {
+                return None;
+            }
+        }
+
+        Some(self.finish())
+    }
+
+    pub fn play_turn(&mut self) -> TurnResult {
+        let opponents = self.players.len() - 1;
+        let player = &mut self.players[self.turn];
+        let rack = player.rack.get_tiles_vec();
+        let bag = self.bag.clone();
+        let result = player.play_turn(&mut self.board, &mut self.bag, opponents, self.scoreless_turns);
+        let mut placed = Vec::new();
+
+        let action = match &result {
+            TurnResult::Command(command) => {
+                self.run_command(command);
+                return result;
+            }
+            TurnResult::Score(_) => match self.board.take_last_play() {
+                Some(play) => {
+                    placed = play.tiles;
+                    Action::Play { word: play.word, row: play.row, col: play.col, across: play.across }
+                }
+                None => Action::Pass,
+            },
+            TurnResult::Withdrawn { play, score } => {
+                // recorded as the play and then its withdrawal, as in a game
+                // record, so that undo takes back both
+                let player = &self.players[self.turn];
+                let action = Action::Play { word: play.word.clone(), row: play.row, col: play.col, across: play.across };
+                let total = player.score + score;
+                self.history.push(HistoryEntry { player: player.id, rack: rack.clone(), action, score: *score, total });
+                self.undo.push(TurnChanges {
+                    placed: Vec::new(),
+                    bag: bag.clone(),
+                    rack_after: rack.clone(),
+                    scoreless_turns: self.scoreless_turns,
+                });
+                Action::Withdrawn
+            }
+            TurnResult::Swap(tiles) => Action::Exchange(tiles.clone()),
+            TurnResult::Pass => Action::Pass,
+        };
+
+        let score = match result {
+            TurnResult::Score(n) => n,
+            TurnResult::Withdrawn { score, .. } => -score,
+            _ => 0,
+        };
+        let scoreless_turns = self.scoreless_turns;
+        if action.is_scoreless() {
+            self.scoreless_turns += 1;
+        } else {
+            self.scoreless_turns = 0;
+        }
+
+        let player = &self.players[self.turn];
+        let rack_after = player.rack.get_tiles_vec();
+        self.history.push(HistoryEntry { player: player.id, rack, action, score, total: player.score });
+        self.undo.push(TurnChanges { placed, bag, rack_after, scoreless_turns });
+        self.redo.clear();
+
+        self.turn = (self.turn + 1) % self.players.len();
+        result
+    }
+
+    // Commands leave the turn with the same player.
+    fn run_command(&mut self, command: &Command) {
+        match command {
+            Command::Save(path) => match self.save(path) {
+                Ok(()) => println!("Game saved to {path}."),
+                Err(e) => println!("Could not save to {path}: {e}"),
+            },
+            Command::Load(path) => {
+                let seed = self.bag.next_seed();
+                match Session::load(path, self.board.lexicon().clone(), self.board.rules().clone(), seed) {
+                    Ok(mut session) => {
+                        session.keep_strategies(&mut self.players);
+                        *self = session;
+                        println!("Game loaded from {path}.");
+                    }
+                    Err(e) => println!("Could not load {path}: {e}"),
+                }
+            }
+            Command::Undo => self.undo_turns(),
+            Command::Redo => self.redo_turns(),
+            Command::Exit => {}
+        }
+    }
+
+    // Takes back the last turn: its tiles come off the board, the bag is as it
+    // was before and the player gets their rack and score back. Returns false
+    // if there is no turn to undo, e.g. right after loading.
+    pub fn undo(&mut self) -> bool {
+        let Some(mut changes) = self.undo.pop() else {
+            return false;
+        };
+        let entry = self.history.pop().expect("Every undoable turn has a history entry.");
+        let i = self.player_index(entry.player);
+
+        self.board.take_back(&changes.placed);
+        std::mem::swap(&mut self.bag, &mut changes.bag);
+
+        let player = &mut self.players[i];
+        player.rack = rack_of(&entry.rack);
+        player.score = entry.total - entry.score;
+        self.scoreless_turns = changes.scoreless_turns;
+        self.turn = i;
+
+        // a withdrawn play goes with the play it took back
+        let withdrawn = entry.action == Action::Withdrawn;
+        self.redo.push((entry, changes));
+        if withdrawn {
+            self.undo();
+        }
+        true
+    }
+
+    // Plays the last undone turn again, drawing the same tiles. Returns false
+    // if there is none, or a turn has been played since the undo.
+    pub fn redo(&mut self) -> bool {
+        let Some((entry, mut changes)) = self.redo.pop() else {
+            return false;
+        };
+        let i = self.player_index(entry.player);
+
+        self.board.restore_tiles(&changes.placed).expect("Undo emptied the squares of the play.");
+        std::mem::swap(&mut self.bag, &mut changes.bag);
+
+        let player = &mut self.players[i];
+        player.rack = rack_of(&changes.rack_after);
+        player.score = entry.total;
+        self.scoreless_turns = if entry.action.is_scoreless() { changes.scoreless_turns + 1 } else { 0 };
+        self.turn = (i + 1) % self.players.len();
+
+        self.history.push(entry);
+        self.undo.push(changes);
+        if self.redo.last().is_some_and(|(entry, _)| entry.action == Action::Withdrawn) {
+            self.redo();
+        }
+        true
+    }
+
+    // Undoes turns until it is a person's turn again, so that a bot's reply
+    // is taken back along with the move it answered.
+    fn undo_turns(&mut self) {
+        if !self.undo() {
+            println!("Nothing to undo.");
+            return;
+        }
+        loop {
+            println!("Took back player {}'s turn.", self.players[self.turn].id);
+            if self.players[self.turn].strategy.interactive() || !self.undo() {
+                break;
+            }
+        }
+    }
+
+    // Redoes turns until it is a person's turn again.
+    fn redo_turns(&mut self) {
+        if self.redo.is_empty() {
+            println!("Nothing to redo.");
+            return;
+        }
+        while self.redo() {
+            let entry = self.history.last().expect("Just redone.");
+            println!("Played player {}'s turn again.", entry.player);
+            if self.players[self.turn].strategy.interactive() {
+                break;
+            }
+        }
+    }
+
+    fn player_index(&self, id: i32) -> usize {
+        self.players.iter().position(|player| player.id == id).expect("History entries are for players in the game.")
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.scoreless_turns >= MAX_SCORELESS_TURNS || self.went_out().is_some()
+    }
+
+    // the player who emptied their rack after the bag ran out, if any.
+    pub fn went_out(&self) -> Option<usize> {
+        self.players.iter().position(|player| player.is_out(&self.bag))
+    }
+
+    // Deducts what is left on each rack. A player who went out also collects
+    // the value of everyone else's rack.
+    pub fn finish(&mut self) -> Standings {
+        let went_out = self.went_out();
+        let mut collected = 0;
+        let mut leftover = Vec::new();
+        let mut standings = Vec::new();
+
+        for player in &mut self.players {
+            let penalty = player.rack.value(self.board.rules());
+            player.score -= penalty;
+            collected += penalty;
+            standings.push(Standing { id: player.id, score: player.score, rack_adjustment: -penalty });
+
+            let rack = player.rack.get_tiles_vec();
+            if !rack.is_empty() {
+                leftover.extend(&rack);
+                self.history.push(HistoryEntry {
+                    player: player.id,
+                    rack: rack.clone(),
+                    action: Action::EndRack(rack),
+                    score: -penalty,
+                    total: player.score,
+                });
+            }
+        }
+
+        if let Some(i) = went_out {
+            self.players[i].score += collected;
+            standings[i].score += collected;
+            standings[i].rack_adjustment += collected;
+            self.history.push(HistoryEntry {
+                player: self.players[i].id,
+                rack: Vec::new(),
+                action: Action::EndRack(leftover),
+                score: collected,
+                total: self.players[i].score,
+            });
+        }
+
+        let best = standings.iter().map(|standing| standing.score).max();
+        let mut leaders = standings.iter().filter(|standing| Some(standing.score) == best);
+        let winner = match (leaders.next(), leaders.next()) {
+            (Some(leader), None) => Some(leader.id),
+            _ => None,
+        };
+
+        Standings { standings, winner }
+    }
+}
+
+fn rack_of(tiles: &[char]) -> Rack {
+    let mut rack = Rack::new();
+    for &tile in tiles {
+        rack.add_tile(tile);
+    }
+    rack
+}
+
+fn is_gcg(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("gcg"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::MIN_BAG_FOR_SWAP;
+    use crate::strategy::{CpuBot, GameView, Move, Strategy, Turn};
+
+    fn session_with_racks(racks: &[&str]) -> Session {
+        let players = (1..).zip(racks).map(|(id, _)| Player::new(id, Box::new(CpuBot::default()))).collect();
+        let mut bag = Bag::new();
+        bag.tiles.clear();
+
+        let mut session = Session::new(Board::new("dict.txt".to_string()), bag, players);
+        for (player, rack) in session.players.iter_mut().zip(racks) {
+            player.rack = Rack::from_tiles(rack);
+        }
+        session
+    }
+
+    #[test]
+    fn test_going_out() {
+        let mut session = session_with_racks(&["QI", "VV"]);
+
+        let standings = session.run().unwrap();
+        assert_eq!(session.went_out(), Some(0));
+        assert_eq!(standings.standings[0].score, 22 + 8);
+        assert_eq!(standings.standings[0].rack_adjustment, 8);
+        assert_eq!(standings.standings[1].score, -8);
+        assert_eq!(standings.winner, Some(1));
+
+        let first = &session.history[0];
+        assert_eq!(first.player, 1);
+        assert_eq!(first.action, Action::Play { word: "QI".to_string(), row: 7, col: 7, across: true });
+        assert_eq!(first.total, 22);
+    }
+
+    #[test]
+    fn test_cpu_exchanges_without_a_play() {
+        let mut session = session_with_racks(&["QV"]);
+        session.bag = Bag::new();
+
+        assert!(matches!(session.play_turn(), TurnResult::Swap(_)));
+        assert_eq!(session.players[0].rack.len(), 7);
+        assert_eq!(session.scoreless_turns, 1);
+    }
+
+    #[test]
+    fn test_play_of_blanks_is_not_scoreless() {
+        let mut session = session_with_racks(&["**", "VV"]);
+        session.scoreless_turns = MAX_SCORELESS_TURNS - 1;
+
+        assert!(matches!(session.play_turn(), TurnResult::Score(0)));
+        assert_eq!(session.scoreless_turns, 0);
+
+        // and undoing and redoing it keeps the count
+        assert!(session.undo());
+        assert_eq!(session.scoreless_turns, MAX_SCORELESS_TURNS - 1);
+        assert!(session.redo());
+        assert_eq!(session.scoreless_turns, 0);
+    }
+
+    // a person who tries to exchange their whole rack, and passes when asked
+    // again because the exchange was refused.
+    struct Exchanger(bool);
+
+    impl Strategy for Exchanger {
+        fn name(&self) -> &str {
+            "exchanger"
+        }
+
+        fn interactive(&self) -> bool {
+            true
+        }
+
+        fn choose(&mut self, view: &GameView) -> Turn {
+            self.0 = !self.0;
+            Turn::Move(if self.0 { Move::Exchange(view.rack.get_tiles_vec()) } else { Move::Pass })
+        }
+    }
+
+    #[test]
+    fn test_no_exchange_from_a_small_bag() {
+        let mut session = session_with_racks(&["QV"]);
+        session.players[0] = Player::new(1, Box::new(Exchanger(false)));
+        session.players[0].rack = Rack::from_tiles("QVVWWXZ");
+        session.bag = Bag::from_tiles(vec!['A'; MIN_BAG_FOR_SWAP - 1]);
+
+        assert!(matches!(session.play_turn(), TurnResult::Pass));
+        assert_eq!(session.players[0].rack.get_tiles_vec(), vec!['Q', 'V', 'V', 'W', 'W', 'X', 'Z']);
+        assert_eq!(session.bag.size(), MIN_BAG_FOR_SWAP - 1);
+
+        session.bag.add_tile('A');
+        assert!(matches!(session.play_turn(), TurnResult::Swap(_)));
+    }
+
+    // saved as a cpu player. Loads a game on its first turn and then exits,
+    // where the default cpu player it would be loaded as plays instead.
+    struct Loader(Option<String>);
+
+    impl Strategy for Loader {
+        fn name(&self) -> &str {
+            "cpu"
+        }
+
+        fn choose(&mut self, _view: &GameView) -> Turn {
+            match self.0.take() {
+                Some(path) => Turn::Command(Command::Load(path)),
+                None => Turn::Command(Command::Exit),
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_keeps_strategies() {
+        let path = std::env::temp_dir().join(format!("words-load-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let mut session = session_with_racks(&["QI", "VV"]);
+        session.save(&path).unwrap();
+
+        session.players[0].strategy = Box::new(Loader(Some(path.clone())));
+        assert!(matches!(session.play_turn(), TurnResult::Command(Command::Load(_))));
+        assert_eq!(session.turn, 0);
+        assert!(matches!(session.play_turn(), TurnResult::Command(Command::Exit)));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // a player without a rack whose play of ZZZ is challenged off the board.
+    struct Phony;
+
+    impl Strategy for Phony {
+        fn name(&self) -> &str {
+            "phony"
+        }
+
+        fn rackless(&self) -> bool {
+            true
+        }
+
+        fn choose(&mut self, _view: &GameView) -> Turn {
+            Turn::Withdrawn { word: String::from("ZZZ"), row: 7, col: 7, across: true }
+        }
+    }
+
+    #[test]
+    fn test_withdrawn_play() {
+        let mut session = session_with_racks(&["", "VV"]);
+        session.players[0] = Player::new(1, Box::new(Phony));
+
+        assert!(matches!(session.play_turn(), TurnResult::Withdrawn { score: 60, .. }));
+        assert_eq!(session.players[0].score, 0);
+        assert_eq!(session.board.get_tile(7, 7), '-');
+        assert_eq!(session.scoreless_turns, 1);
+        assert_eq!(session.turn, 1);
+        let play = Action::Play { word: String::from("ZZZ"), row: 7, col: 7, across: true };
+        let entries: Vec<_> = session.history.iter().map(|entry| (entry.action.clone(), entry.score, entry.total)).collect();
+        assert_eq!(entries, vec![(play, 60, 60), (Action::Withdrawn, -60, 0)]);
+
+        // written and read back as a play followed by "--"
+        let mut record = Vec::new();
+        gcg::write(&session, &mut record).unwrap();
+        let text = String::from_utf8(record.clone()).unwrap();
+        assert!(text.contains("8H ZZZ +60 60\n"), "{text}");
+        assert!(text.contains("-- -60 0\n"), "{text}");
+        let read = gcg::read(&mut record.as_slice(), session.board.lexicon().clone(), session.board.rules().clone(), 1).unwrap();
+        assert_eq!(read.history, session.history);
+        assert_eq!(read.scoreless_turns, 1);
+
+        // undone and redone as one turn
+        let history = session.history.clone();
+        assert!(session.undo());
+        assert!(session.history.is_empty());
+        assert_eq!((session.turn, session.scoreless_turns), (0, 0));
+        assert!(session.redo());
+        assert_eq!(session.history, history);
+        assert_eq!((session.turn, session.scoreless_turns, session.players[0].score), (1, 1, 0));
+    }
+
+    #[test]
+    fn test_scoreless_turns() {
+        let mut session = session_with_racks(&["QV", "VQ"]);
+
+        let standings = session.run().unwrap();
+        assert_eq!(session.scoreless_turns, MAX_SCORELESS_TURNS);
+        assert_eq!(session.went_out(), None);
+        assert_eq!(standings.standings[0].score, -14);
+        assert_eq!(standings.standings[1].score, -14);
+        assert_eq!(standings.winner, None);
+    }
+
+    // board, bag, racks and scores, turn, scoreless turns and history length.
+    type State = (Vec<Vec<char>>, Vec<char>, Vec<(Vec<char>, i32)>, usize, u32, usize);
+
+    // everything undo and redo put back, down to the order of the bag.
+    fn state(session: &Session) -> State {
+        let bag = session.bag.tiles.clone();
+        let players = session.players.iter().map(|player| (player.rack.get_tiles_vec(), player.score)).collect();
+        (session.board.get_board_chars(), bag, players, session.turn, session.scoreless_turns, session.history.len())
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let players = vec![Player::new(1, Box::new(CpuBot::default())), Player::new(2, Box::new(CpuBot::default()))];
+        let mut session = Session::new(Board::new("dict.txt".to_string()), Bag::with_seed(7), players);
+        assert!(!session.undo());
+
+        let start = state(&session);
+        for _ in 0..3 {
+            session.play_turn();
+        }
+        let played = state(&session);
+        assert_ne!(played, start);
+
+        while session.undo() {}
+        assert_eq!(state(&session), start);
+        assert_eq!(session.board.get_neighbors(), vec![session.board.layout().start]);
+
+        while session.redo() {}
+        assert_eq!(state(&session), played);
+
+        // a new turn after an undo cannot be redone over
+        assert!(session.undo());
+        session.play_turn();
+        assert!(!session.redo());
+    }
+
+    #[test]
+    fn test_undo_exchange() {
+        let mut session = session_with_racks(&["QV"]);
+        session.bag = Bag::with_seed(3);
+        let start = state(&session);
+
+        assert!(matches!(session.play_turn(), TurnResult::Swap(_)));
+        let exchanged = state(&session);
+        let bag = session.bag.clone();
+        assert!(session.undo());
+        assert_eq!(state(&session), start);
+        assert!(session.redo());
+        assert_eq!(state(&session), exchanged);
+
+        // the bag shuffles the same way after the redo as it would have
+        assert_eq!(session.bag, bag);
+    }
+}
@@ -1,14 +1,70 @@
-use crate::game::{Bag, Board, Rack};
-use crate::greedy::find_greediest_word;
-use crate::player::Player;
+use crate::endgame::{deduce_opponent_rack, EndgameConfig, EndgameSolver};
+use crate::equity::leave_after;
+use crate::game::{blank_for, format_square, letter_of, parse_square, rack_for, Board, Placement, Rack};
+use crate::greedy::{find_all_words, find_greediest_word, WordChoice};
+use crate::player::MIN_BAG_FOR_SWAP;
+use crate::strategy::{Command, GameView, Move, Strategy, Turn};
+use crate::tileset::TileSet;
+
+// how many plays the moves command lists unless told otherwise.
+const DEFAULT_MOVES: usize = 10;
+
+// Plays whatever the person at the keyboard types, from their own rack.
+pub struct HumanShell;
+
+impl Strategy for HumanShell {
+    fn name(&self) -> &str {
+        "human"
+    }
+
+    fn interactive(&self) -> bool {
+        true
+    }
+
+    fn choose(&mut self, view: &GameView) -> Turn {
+        Shell::new(view, false).main_loop()
+    }
+}
+
+// The shell for someone playing elsewhere, e.g. on a real board. They enter
+// the plays they make and can ask for the best play with any rack.
+pub struct RacklessHelper;
+
+impl Strategy for RacklessHelper {
+    fn name(&self) -> &str {
+        "human-no-rack"
+    }
+
+    fn rackless(&self) -> bool {
+        true
+    }
+
+    fn interactive(&self) -> bool {
+        true
+    }
+
+    fn choose(&mut self, view: &GameView) -> Turn {
+        Shell::new(view, true).main_loop()
+    }
+}
 
 enum ShellCommand {
+    Challenge,
+    Check(Vec<String>),
+    Endgame(Vec<String>),
     Exit,
     Help(Vec<String>),
+    Load(Vec<String>),
+    Moves(Vec<String>),
+    Pass,
+    Play(Vec<String>),
     Put(Vec<String>),
+    Redo,
+    Save(Vec<String>),
     Show,
     Submit,
     Swap(Vec<String>),
+    Undo,
     Unstage,
     WriteAcross(Vec<String>),
     WriteDown(Vec<String>),
@@ -18,27 +74,42 @@ enum ShellCommand {
 pub enum ShellStatus {
     Continue,
     Exit,
-    Submit(i32),
-    Swap,
+    Submit(Placement),
+    // the staged play was challenged off the board.
+    Withdrawn(Placement),
+    Swap(Vec<char>),
+    Pass,
+    Save(String),
+    Load(String),
+    Undo,
+    Redo,
     Err(String),
 }
 
 pub struct Shell<'a> {
-    bag: &'a mut Bag,
-    board: &'a mut Board,
-    player: &'a mut Player,
+    view: &'a GameView<'a>,
+    // Copies of the board and rack to stage tiles on. The game itself only
+    // changes once the shell has returned a move.
+    board: Board,
+    rack: Rack,
+    rackless: bool,
 }
 
 impl<'a> Shell<'a> {
-    pub fn new(bag: &'a mut Bag, board: &'a mut Board, player: &'a mut Player) -> Self {
+    pub fn new(view: &'a GameView<'a>, rackless: bool) -> Self {
         Shell {
-            bag: bag,
-            board: board,
-            player: player,
+            view,
+            board: view.board.clone(),
+            rack: view.rack.clone(),
+            rackless,
         }
     }
 
-    pub fn main_loop(&mut self) -> ShellStatus {
+    fn tile_set(&self) -> &TileSet {
+        &self.board.rules().tile_set
+    }
+
+    pub fn main_loop(&mut self) -> Turn {
         loop {
             print!("> ");
             let line = self.read_line();
@@ -47,9 +118,19 @@ impl<'a> Shell<'a> {
 
             match status {
                 ShellStatus::Continue => continue,
-                ShellStatus::Exit => return status,
-                ShellStatus::Submit(_) => return status,
-                ShellStatus::Swap => return status,
+                ShellStatus::Exit => return Turn::Command(Command::Exit),
+                ShellStatus::Submit(play) => {
+                    return Turn::Move(Move::Play { word: play.word, row: play.row, col: play.col, across: play.across });
+                }
+                ShellStatus::Withdrawn(play) => {
+                    return Turn::Withdrawn { word: play.word, row: play.row, col: play.col, across: play.across };
+                }
+                ShellStatus::Swap(tiles) => return Turn::Move(Move::Exchange(tiles)),
+                ShellStatus::Pass => return Turn::Move(Move::Pass),
+                ShellStatus::Save(path) => return Turn::Command(Command::Save(path)),
+                ShellStatus::Load(path) => return Turn::Command(Command::Load(path)),
+                ShellStatus::Undo => return Turn::Command(Command::Undo),
+                ShellStatus::Redo => return Turn::Command(Command::Redo),
                 ShellStatus::Err(msg) => {
                     print!("{msg}");
                     continue;
@@ -76,26 +157,35 @@ impl<'a> Shell<'a> {
     }
 
     fn parse(&self, line: String) -> ShellCommand {
-        let cmd;
         let mut args = Vec::new();
 
         let mut iter = line.split_whitespace();
-        cmd = match iter.next() {
+        let cmd = match iter.next() {
             Some(s) => s.to_string(),
             None => String::new(),
         };
 
-        while let Some(s) = iter.next() {
+        for s in iter {
             args.push(s.to_string());
         }
 
         match cmd.as_str() {
+            "challenge" => ShellCommand::Challenge,
+            "check" => ShellCommand::Check(args),
+            "endgame" => ShellCommand::Endgame(args),
             "exit" => ShellCommand::Exit,
             "help" => ShellCommand::Help(args),
+            "load" => ShellCommand::Load(args),
+            "moves" => ShellCommand::Moves(args),
+            "pass" => ShellCommand::Pass,
+            "play" => ShellCommand::Play(args),
             "put" => ShellCommand::Put(args),
+            "redo" => ShellCommand::Redo,
+            "save" => ShellCommand::Save(args),
             "show" => ShellCommand::Show,
             "submit" => ShellCommand::Submit,
             "swap" => ShellCommand::Swap(args),
+            "undo" => ShellCommand::Undo,
             "unstage" => ShellCommand::Unstage,
             "wa" => ShellCommand::WriteAcross(args),
             "wd" => ShellCommand::WriteDown(args),
@@ -103,107 +193,81 @@ impl<'a> Shell<'a> {
         }
     }
 
-    fn parse_put(&self, mut args: Vec<String>) -> Option<(char, usize, usize)> {
-        let letter;
-        let row;
-        let col;
-        if args.len() != 3 {
-            println!("Error: Syntax: put [LETTER] [ROW_INDEX] [COLUMN_INDEX]");
+    fn parse_put(&self, args: Vec<String>) -> Option<(char, usize, usize)> {
+        let Some((letter, position)) = args.split_first().filter(|(_, position)| !position.is_empty()) else {
+            println!("Error: Syntax: put LETTER SQUARE, or put LETTER ROW_INDEX COLUMN_INDEX");
             return None;
-        }
-        col = args.pop().expect("Vector is length 3.");
-        row = args.pop().expect("Vector is length 3.");
-        letter = args.pop().expect("Vector is length 3.");
+        };
 
-        if letter.len() != 1 {
+        let letter = parse_tiles(letter, self.tile_set())?;
+        if letter.chars().count() != 1 {
             println!("Error: More than one letter provided.");
             return None;
         }
         let letter: char = letter.chars().nth(0).expect("Letter is length 1.");
-        if !letter.is_alphabetic() {
-            println!("Error: Syntax: put [LETTER] [ROW_INDEX] [COLUMN_INDEX]");
-            return None;
-        }
-        let row: usize = match row.parse() {
-            Ok(n) => {
-                if n > 14 {
-                    println!("Row out of bounds.");
-                    return None;
-                } else {
-                    n
-                }
-            }
-            Err(_) => return None,
-        };
-
-        let col: usize = match col.parse() {
-            Ok(n) => {
-                if n > 14 {
-                    println!("Col out of bounds.");
-                    return None;
-                } else {
-                    n
-                }
-            }
-            Err(_) => return None,
-        };
+        let (row, col) = self.parse_position(position)?;
 
         Some((letter, row, col))
     }
 
-    fn parse_write(&self, mut args: Vec<String>) -> Option<(String, usize, usize)> {
-        let word;
-        let row;
-        let col;
-        if args.len() != 3 {
-            println!("Error: Syntax: put [WORD] [ROW_INDEX] [COLUMN_INDEX]");
+    fn parse_write(&self, args: Vec<String>) -> Option<(String, usize, usize)> {
+        let Some((word, position)) = args.split_first().filter(|(_, position)| !position.is_empty()) else {
+            println!("Error: Syntax: wa|wd WORD SQUARE, or wa|wd WORD ROW_INDEX COLUMN_INDEX");
             return None;
-        }
-        col = args.pop().expect("Vector is length 3.");
-        row = args.pop().expect("Vector is length 3.");
-        word = args.pop().expect("Vector is length 3.");
-
-        for letter in word.chars() {
-            if !letter.is_alphabetic() {
-                println!("Error: Non-alphabetic letter detected.");
-                return None;
-            }
-        }
-        let row: usize = match row.parse() {
-            Ok(n) => {
-                if n > 14 {
-                    println!("Row out of bounds.");
-                    return None;
-                } else {
-                    n
-                }
-            }
-            Err(_) => return None,
         };
+        let word = parse_tiles(word, self.tile_set())?;
+        let (row, col) = self.parse_position(position)?;
+
+        Some((word, row, col))
+    }
 
-        let col: usize = match col.parse() {
-            Ok(n) => {
-                if n > 14 {
-                    println!("Col out of bounds.");
+    // A square in standard notation like 8H, or a row and column counted
+    // from 0. The direction of the square does not matter here.
+    fn parse_position(&self, args: &[String]) -> Option<(usize, usize)> {
+        let (row, col) = match args {
+            [square] => match parse_square(square, self.board.size()) {
+                Ok((row, col, _)) => (row, col),
+                Err(e) => {
+                    println!("Error: {e}");
                     return None;
-                } else {
-                    n
                 }
+            },
+            [row, col] => (row.parse().ok()?, col.parse().ok()?),
+            _ => {
+                println!("Error: Expected a square like 8H or a row and column.");
+                return None;
             }
-            Err(_) => return None,
         };
 
-        Some((word, row, col))
+        if row >= self.board.size() {
+            println!("Row out of bounds.");
+            return None;
+        }
+        if col >= self.board.size() {
+            println!("Col out of bounds.");
+            return None;
+        }
+        Some((row, col))
     }
 
     fn execute(&mut self, cmd: ShellCommand) -> ShellStatus {
         match cmd {
+            ShellCommand::Challenge => self.exec_challenge(),
+            ShellCommand::Check(args) => self.exec_check(args),
+            ShellCommand::Endgame(args) => self.exec_endgame(args),
             ShellCommand::Exit => ShellStatus::Exit,
             ShellCommand::Help(args) => self.exec_help(args),
+            ShellCommand::Load(args) => self.exec_load(args),
+            ShellCommand::Moves(args) => self.exec_moves(args),
+            ShellCommand::Pass => self.exec_pass(),
+            ShellCommand::Play(args) => self.exec_play(args),
             ShellCommand::Put(args) => self.exec_put(args),
+            ShellCommand::Redo => self.exec_redo(),
+            ShellCommand::Save(args) => self.exec_save(args),
             ShellCommand::Show => self.exec_show(),
             ShellCommand::Submit => self.exec_submit(),
             ShellCommand::Swap(args) => self.exec_swap(args),
+            ShellCommand::Undo => self.exec_undo(),
             ShellCommand::Unstage => self.exec_unstage(),
             ShellCommand::WriteAcross(args) => self.exec_write_across(args),
             ShellCommand::WriteDown(args) => self.exec_write_down(args),
@@ -211,24 +275,120 @@ impl<'a> Shell<'a> {
         }
     }
 
-    fn exec_help(&mut self, args: Vec<String>) -> ShellStatus {
-        if !self.player.rackless {
-            if args.len() != 0 {
-                return ShellStatus::Err(String::from("Unexpected arguments.\n"));
+    // Challenges the play staged for an opponent, e.g. one seen on a real
+    // board. A play that forms any phony comes off the board and the turn is
+    // lost, which the game records as a withdrawn play. Otherwise the play
+    // stays staged, ready to submit.
+    fn exec_challenge(&mut self) -> ShellStatus {
+        if !self.rackless {
+            return ShellStatus::Err(String::from("Only plays entered without a rack can be challenged.\n"));
+        }
+        let words = self.board.staged_words();
+        let Some(play) = self.board.staged_placement().filter(|_| !words.is_empty()) else {
+            return ShellStatus::Err(String::from("No words to challenge. Stage the play first.\n"));
+        };
+
+        let verdicts = self.check_words(&words);
+        for verdict in &verdicts {
+            println!("{verdict}");
+        }
+        if words.iter().all(|word| self.board.word_in_dict(word.clone())) {
+            println!("The challenge fails. Submit the play to keep it.");
+            return ShellStatus::Continue;
+        }
+        println!("The play comes off the board and the turn is lost.");
+        self.board.unstage();
+        ShellStatus::Withdrawn(play)
+    }
+
+    // check WORD [WORD...]. Says whether each word is in the word list.
+    fn exec_check(&mut self, args: Vec<String>) -> ShellStatus {
+        if args.is_empty() {
+            return ShellStatus::Err(String::from("Syntax: check WORD [WORD...]\n"));
+        }
+        // a blank makes no difference to whether a word is good
+        let words: Vec<String> = args
+            .iter()
+            .filter_map(|arg| parse_tiles(arg, self.tile_set()))
+            .map(|word| word.chars().map(letter_of).collect())
+            .collect();
+        for verdict in self.check_words(&words) {
+            println!("{verdict}");
+        }
+        ShellStatus::Continue
+    }
+
+    // a line for each word saying whether it is in the word list.
+    fn check_words(&self, words: &[String]) -> Vec<String> {
+        words
+            .iter()
+            .map(|word| {
+                let valid = self.board.word_in_dict(word.clone());
+                format!("{} is {}.", self.tile_set().decode(word), if valid { "valid" } else { "not a word" })
+            })
+            .collect()
+    }
+
+    // endgame [PLIES] [RACK] [OPPONENT_RACK]. Searches to the end of the game
+    // unless given a depth. Players with a rack only give the opponent's rack
+    // if it cannot be worked out from the unseen tiles.
+    fn exec_endgame(&mut self, args: Vec<String>) -> ShellStatus {
+        let mut config = EndgameConfig::default();
+        let mut racks = Vec::new();
+        for arg in args {
+            match arg.parse() {
+                Ok(n) => config.max_plies = Some(n),
+                Err(_) => racks.push(rack_for(&parse_rack(&arg, self.tile_set()))),
             }
+        }
 
-            let word_choice = find_greediest_word(&mut self.board, &self.player.rack);
-            if word_choice.across {
-                println!(
-                    "Highest scorer is {} at ({}, {}) ACROSS for {} points.",
-                    word_choice.word, word_choice.row, word_choice.col, word_choice.score
-                );
-            } else {
-                println!(
-                    "Highest scorer is {} at ({}, {}) DOWN for {} points.",
-                    word_choice.word, word_choice.row, word_choice.col, word_choice.score
-                );
+        self.exec_unstage();
+        let (rack, opponent) = match (self.rackless, racks.len()) {
+            (true, 2) => {
+                let opponent = racks.pop().expect("Checked that there are two racks.");
+                (racks.pop().expect("Checked that there are two racks."), opponent)
             }
+            (true, _) => return ShellStatus::Err(String::from("Syntax: endgame [PLIES] [RACK] [OPPONENT_RACK]\n")),
+            (false, 1) => (self.rack.clone(), racks.pop().expect("Checked that there is a rack.")),
+            (false, 0) => match deduce_opponent_rack(&self.board, &self.rack, self.view.bag_size, self.view.opponents) {
+                Some(opponent) => (self.rack.clone(), opponent),
+                None => {
+                    return ShellStatus::Err(String::from(
+                        "The opponent's rack is unknown until the bag is empty. Syntax: endgame [PLIES] [OPPONENT_RACK]\n",
+                    ));
+                }
+            },
+            (false, _) => return ShellStatus::Err(String::from("Syntax: endgame [PLIES] [OPPONENT_RACK]\n")),
+        };
+
+        let result = EndgameSolver::new(config).solve(&self.board, &rack, &opponent, self.view.scoreless_turns);
+        if !result.exact {
+            println!("Searched {} plies ahead, short of the end of the game.", result.plies);
+        }
+        println!("Best line is worth {:+} for you:", result.spread);
+        for (i, play) in result.line.iter().enumerate() {
+            let who = if i % 2 == 0 { "You" } else { "Opponent" };
+            match play {
+                Some(choice) => println!(
+                    "{}. {} play {} at {} for {} points.",
+                    i + 1,
+                    who,
+                    format_tiles(&choice.word, self.tile_set()),
+                    format_square(choice.row, choice.col, choice.across),
+                    choice.score
+                ),
+                None => println!("{}. {} pass.", i + 1, who),
+            }
+        }
+        ShellStatus::Continue
+    }
+
+    fn exec_help(&mut self, args: Vec<String>) -> ShellStatus {
+        let rack = if !self.rackless {
+            if !args.is_empty() {
+                return ShellStatus::Err(String::from("Unexpected arguments.\n"));
+            }
+            self.rack.clone()
         } else {
             if args.len() != 1 {
                 return ShellStatus::Err(String::from(
@@ -236,52 +396,166 @@ impl<'a> Shell<'a> {
                 ));
             }
 
-            let helpstr = args.first().expect("Checked that first exists.").chars();
+            let helpstr = parse_rack(args.first().expect("Checked that first exists."), self.tile_set());
             let mut help_rack = Rack::new();
-            for c in helpstr {
+            for c in helpstr.chars() {
                 help_rack.add_tile(c);
             }
             if help_rack.is_empty() {
                 return ShellStatus::Err(String::from("Expected non-empty rack.\n"));
             }
-            let word_choice = find_greediest_word(self.board, &help_rack);
-            if word_choice.across {
-                println!(
-                    "Highest scorer is {} at ({}, {}) ACROSS for {} points.",
-                    word_choice.word, word_choice.row, word_choice.col, word_choice.score
-                );
+            help_rack
+        };
+
+        let Some(word_choice) = find_greediest_word(&self.board, &rack) else {
+            if self.view.bag_size >= MIN_BAG_FOR_SWAP {
+                println!("No plays found. Exchange some tiles with swap, or pass.");
             } else {
-                println!(
-                    "Highest scorer is {} at ({}, {}) DOWN for {} points.",
-                    word_choice.word, word_choice.row, word_choice.col, word_choice.score
-                );
+                println!("No plays found, and the bag is too small to exchange. Pass.");
             }
+            return ShellStatus::Continue;
+        };
+        println!(
+            "Highest scorer is {} at {} for {} points.",
+            format_tiles(&word_choice.word, self.tile_set()),
+            format_square(word_choice.row, word_choice.col, word_choice.across),
+            word_choice.score
+        );
+        ShellStatus::Continue
+    }
+
+    fn exec_load(&mut self, args: Vec<String>) -> ShellStatus {
+        if args.len() != 1 {
+            return ShellStatus::Err(String::from("Syntax: load [FILE]\n"));
+        }
+        self.exec_unstage();
+        ShellStatus::Load(args[0].clone())
+    }
+
+    // moves [N] [RACK] [across|down] [row ROW|col COL] [using TILE]. Lists the
+    // N highest scoring plays, 10 by default, with the given rack or your own.
+    fn exec_moves(&mut self, args: Vec<String>) -> ShellStatus {
+        let query = match MovesQuery::parse(args, self.tile_set(), self.board.size()) {
+            Ok(query) => query,
+            Err(e) => return ShellStatus::Err(e),
+        };
+
+        self.exec_unstage();
+        let rack = match &query.rack {
+            Some(rack) => rack.clone(),
+            None if !self.rackless => self.rack.clone(),
+            None => return ShellStatus::Err(String::from("Which rack? ") + MOVES_SYNTAX),
+        };
+        if rack.is_empty() {
+            return ShellStatus::Err(String::from("Expected non-empty rack.\n"));
+        }
+
+        let moves = query.find(&self.board, &rack);
+        if moves.is_empty() {
+            println!("No plays found.");
+            return ShellStatus::Continue;
+        }
+
+        let rack = rack.get_tiles_vec();
+        println!(
+            "{:>3}  {:<15} {:<6} {:<6} {:>5}  {:<8} {:<8} Cross-words",
+            "#", "Word", "Square", "Dir", "Score", "Tiles", "Leave"
+        );
+        for (i, choice) in moves.iter().enumerate() {
+            println!("{:>3}  {}", i + 1, format_move(choice, &rack, self.tile_set()));
         }
         ShellStatus::Continue
     }
 
+    fn exec_pass(&mut self) -> ShellStatus {
+        if self.rackless {
+            self.board.unstage();
+        } else {
+            self.board.unstage_to_rack(&mut self.rack);
+        }
+        ShellStatus::Pass
+    }
+
+    // play SQUARE WORD, e.g. play 8H WORD across or play H8 WORD down. Stages
+    // the word and submits it in one go.
+    fn exec_play(&mut self, args: Vec<String>) -> ShellStatus {
+        let [square, word] = &args[..] else {
+            return ShellStatus::Err(String::from("Syntax: play SQUARE WORD, e.g. play 8H WORD or play H8 WORD\n"));
+        };
+        let (row, col, across) = match parse_square(square, self.board.size()) {
+            Ok(square) => square,
+            Err(e) => return ShellStatus::Err(format!("Error: {e}\n")),
+        };
+        let Some(word) = parse_tiles(word, self.tile_set()) else {
+            return ShellStatus::Err(String::from("Could not parse arguments.\n"));
+        };
+
+        self.exec_unstage();
+        let display = format_tiles(&word, self.tile_set());
+        let mut own_rack = rack_for(&word);
+        let rack = if self.rackless { &mut own_rack } else { &mut self.rack };
+        let placed = if across {
+            self.board.write_across_from_rack(rack, word, row, col)
+        } else {
+            self.board.write_down_from_rack(rack, word, row, col)
+        };
+        if let Err(e) = placed {
+            return ShellStatus::Err(format!("Cannot play {display}: {e}\n"));
+        }
+
+        let status = self.exec_submit();
+        if matches!(status, ShellStatus::Err(_)) {
+            self.exec_unstage();
+        }
+        status
+    }
+
     fn exec_put(&mut self, args: Vec<String>) -> ShellStatus {
         let (letter, row, col) = match self.parse_put(args) {
             Some((letter, row, col)) => (letter, row, col),
             None => return ShellStatus::Err(String::from("Could not parse arguments.\n")),
         };
-        self.board.put_tile(letter, row, col);
+        let placed = if self.rackless {
+            let mut rack = rack_for(&letter.to_string());
+            self.board.put_tile_from_rack(&mut rack, letter, row, col)
+        } else {
+            self.board.put_tile_from_rack(&mut self.rack, letter, row, col)
+        };
 
-        ShellStatus::Continue
+        match placed {
+            Ok(()) => ShellStatus::Continue,
+            Err(e) => ShellStatus::Err(format!("Cannot put {}: {e}\n", format_tiles(&letter.to_string(), self.tile_set()))),
+        }
+    }
+
+    fn exec_redo(&mut self) -> ShellStatus {
+        self.exec_unstage();
+        ShellStatus::Redo
+    }
+
+    fn exec_save(&mut self, args: Vec<String>) -> ShellStatus {
+        if args.len() != 1 {
+            return ShellStatus::Err(String::from("Syntax: save [FILE]\n"));
+        }
+        // staged tiles are not part of the game yet
+        self.exec_unstage();
+        ShellStatus::Save(args[0].clone())
     }
 
     fn exec_show(&self) -> ShellStatus {
         self.board.show();
-        self.player.rack.show();
+        self.rack.show(self.tile_set());
         ShellStatus::Continue
     }
 
     fn exec_submit(&mut self) -> ShellStatus {
-        let score_delta = self.board.submit();
-        if score_delta == 0 {
-            ShellStatus::Continue
-        } else {
-            ShellStatus::Submit(score_delta)
+        // only the copy of the board is changed, the real play comes later
+        match self.board.submit() {
+            Ok(_) => match self.board.take_last_play() {
+                Some(play) => ShellStatus::Submit(play),
+                None => ShellStatus::Err(String::from("Not accepting submission: nothing was played.\n")),
+            },
+            Err(e) => ShellStatus::Err(format!("Not accepting submission: {e}\n")),
         }
     }
 
@@ -292,14 +566,23 @@ impl<'a> Shell<'a> {
             ));
         }
 
-        let swapstr = args.first().expect("Checked that first exists.").chars();
-        let mut to_swap = Vec::new();
-        for c in swapstr {
-            to_swap.push(c.to_ascii_uppercase());
+        if self.view.bag_size < MIN_BAG_FOR_SWAP {
+            return ShellStatus::Err(format!(
+                "Unable to swap. The bag has {} tiles left and exchanges need at least {MIN_BAG_FOR_SWAP}.\n",
+                self.view.bag_size
+            ));
         }
 
-        if self.player.rack.swap(&mut self.bag, to_swap) {
-            ShellStatus::Swap
+        let to_swap: Vec<char> = parse_rack(args.first().expect("Checked that first exists."), self.tile_set()).chars().collect();
+
+        let mut rack = self.rack.clone();
+        let in_rack = to_swap.iter().all(|&tile| {
+            let found = rack.has_tile(tile);
+            rack.use_tile(tile);
+            found
+        });
+        if in_rack {
+            ShellStatus::Swap(to_swap)
         } else {
             ShellStatus::Err(String::from(
                 "Unable to swap. Did you try to swap a letter that wasn't in your rack?\n",
@@ -307,11 +590,16 @@ impl<'a> Shell<'a> {
         }
     }
 
+    fn exec_undo(&mut self) -> ShellStatus {
+        self.exec_unstage();
+        ShellStatus::Undo
+    }
+
     fn exec_unstage(&mut self) -> ShellStatus {
-        if self.player.rackless {
+        if self.rackless {
             self.board.unstage();
         } else {
-            self.board.unstage_to_rack(&mut self.player.rack);
+            self.board.unstage_to_rack(&mut self.rack);
         }
         ShellStatus::Continue
     }
@@ -321,10 +609,19 @@ impl<'a> Shell<'a> {
             Some((word, row, col)) => (word, row, col),
             None => return ShellStatus::Err(String::from("Could not parse arguments.\n")),
         };
-        self.board
-            .write_across_from_rack(&mut self.player.rack, word, row, col);
+        let display = format_tiles(&word, self.tile_set());
+        let placed = if self.rackless {
+            let mut rack = rack_for(&word);
+            self.board.write_across_from_rack(&mut rack, word, row, col)
+        } else {
+            self.board
+                .write_across_from_rack(&mut self.rack, word, row, col)
+        };
 
-        ShellStatus::Continue
+        match placed {
+            Ok(()) => ShellStatus::Continue,
+            Err(e) => ShellStatus::Err(format!("Cannot write {display}: {e}\n")),
+        }
     }
 
     fn exec_write_down(&mut self, args: Vec<String>) -> ShellStatus {
@@ -332,9 +629,309 @@ impl<'a> Shell<'a> {
             Some((word, row, col)) => (word, row, col),
             None => return ShellStatus::Err(String::from("Could not parse arguments.\n")),
         };
-        self.board
-            .write_down_from_rack(&mut self.player.rack, word, row, col);
+        let display = format_tiles(&word, self.tile_set());
+        let placed = if self.rackless {
+            let mut rack = rack_for(&word);
+            self.board.write_down_from_rack(&mut rack, word, row, col)
+        } else {
+            self.board
+                .write_down_from_rack(&mut self.rack, word, row, col)
+        };
 
-        ShellStatus::Continue
+        match placed {
+            Ok(()) => ShellStatus::Continue,
+            Err(e) => ShellStatus::Err(format!("Cannot write {display}: {e}\n")),
+        }
+    }
+}
+
+const MOVES_SYNTAX: &str = "Syntax: moves [N] [RACK] [across|down] [row ROW|col COL] [using TILE]\n";
+
+// What the moves command was asked to list.
+#[derive(Debug)]
+struct MovesQuery {
+    count: usize,
+    rack: Option<Rack>,
+    across: Option<bool>,
+    // the row of across plays or the column of down plays.
+    line: Option<usize>,
+    using: Option<char>,
+}
+
+impl MovesQuery {
+    // The options are only read in lowercase, so that a rack like ROW is
+    // still a rack.
+    fn parse(args: Vec<String>, tile_set: &TileSet, size: usize) -> Result<Self, String> {
+        let mut query = MovesQuery { count: DEFAULT_MOVES, rack: None, across: None, line: None, using: None };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "across" => query.across = Some(true),
+                "down" => query.across = Some(false),
+                // a row holds across plays and a column down plays, numbered
+                // and lettered as on the board
+                "row" => match args.next().map(|row| parse_square(&format!("{row}A"), size)) {
+                    Some(Ok((row, _, _))) => {
+                        query.across = Some(true);
+                        query.line = Some(row);
+                    }
+                    Some(Err(_)) => return Err(format!("No such row. {MOVES_SYNTAX}")),
+                    None => return Err(String::from(MOVES_SYNTAX)),
+                },
+                "col" => match args.next().map(|col| parse_square(&format!("{col}1"), size)) {
+                    Some(Ok((_, col, _))) => {
+                        query.across = Some(false);
+                        query.line = Some(col);
+                    }
+                    Some(Err(_)) => return Err(format!("No such column. {MOVES_SYNTAX}")),
+                    None => return Err(String::from(MOVES_SYNTAX)),
+                },
+                "using" => {
+                    let tile: Vec<char> = match args.next() {
+                        Some(tile) => parse_rack(&tile, tile_set).chars().collect(),
+                        None => Vec::new(),
+                    };
+                    match tile[..] {
+                        [tile] => query.using = Some(tile),
+                        _ => return Err(String::from("Expected a single tile after using.\n")),
+                    }
+                }
+                _ => match arg.parse() {
+                    Ok(n) if query.rack.is_none() => query.count = n,
+                    _ if query.rack.is_none() => {
+                        let tiles = parse_rack(&arg, tile_set);
+                        if !tiles.chars().all(|tile| tile == '*' || tile.is_alphabetic()) {
+                            return Err(format!("Not a rack: {arg}\n"));
+                        }
+                        query.rack = Some(rack_for(&tiles));
+                    }
+                    _ => return Err(String::from(MOVES_SYNTAX)),
+                },
+            }
+        }
+        Ok(query)
+    }
+
+    // The highest scoring plays with the rack that match the query.
+    fn find(&self, board: &Board, rack: &Rack) -> Vec<WordChoice> {
+        find_all_words(board, rack)
+            .filter(|choice| self.across.is_none_or(|across| choice.across == across))
+            .filter(|choice| self.line.is_none_or(|line| if choice.across { choice.row == line } else { choice.col == line }))
+            .filter(|choice| self.using.is_none_or(|tile| choice.tiles.contains(&tile)))
+            .sorted_by_score()
+            .top(self.count)
+            .into_vec()
+    }
+}
+
+// A row of the moves table, without the rank.
+fn format_move(choice: &WordChoice, rack: &[char], tile_set: &TileSet) -> String {
+    let tiles: String = choice.tiles.iter().map(|&tile| tile_set.name(tile)).collect();
+    let leave: String = leave_after(rack, &choice.tiles).into_iter().map(|tile| tile_set.name(tile)).collect();
+    let cross_words: Vec<String> = choice.cross_words.iter().map(|word| format_tiles(word, tile_set)).collect();
+    format!(
+        "{:<15} {:<6} {:<6} {:>5}  {:<8} {:<8} {}",
+        format_tiles(&choice.word, tile_set),
+        format_square(choice.row, choice.col, choice.across),
+        if choice.across { "ACROSS" } else { "DOWN" },
+        choice.score,
+        tiles,
+        leave,
+        cross_words.join(", ")
+    )
+}
+
+// Reads tiles as typed into the shell. Letters are case-insensitive and a blank
+// is written as '*' followed by the letter it stands for. Blanks come back as
+// lowercase letters, and tiles like CH as their stand-ins.
+fn parse_tiles(s: &str, tile_set: &TileSet) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = parse_rack(s, tile_set).chars().collect::<Vec<_>>().into_iter();
+    while let Some(c) = chars.next() {
+        if c == '*' {
+            match chars.next() {
+                Some(letter) if letter.is_alphabetic() => result.push(blank_for(letter)),
+                _ => {
+                    println!("Error: '*' must be followed by the letter the blank stands for.");
+                    return None;
+                }
+            }
+        } else if c.is_alphabetic() {
+            result.push(c);
+        } else {
+            println!("Error: Non-alphabetic letter detected.");
+            return None;
+        }
+    }
+    Some(result)
+}
+
+// The inverse of parse_tiles, so that suggested plays can be typed back in.
+fn format_tiles(tiles: &str, tile_set: &TileSet) -> String {
+    let mut result = String::new();
+    for tile in tiles.chars() {
+        if tile.is_lowercase() {
+            result.push('*');
+            result.push_str(&tile_set.name(tile).to_uppercase());
+        } else {
+            result.push_str(&tile_set.name(tile));
+        }
+    }
+    result
+}
+
+// Tiles as typed for a rack, with '*' for a blank.
+fn parse_rack(s: &str, tile_set: &TileSet) -> String {
+    tile_set.encode(&s.to_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::lexicon::Lexicon;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn board() -> Board {
+        let lexicon = Arc::new(Lexicon::from_words(["AT", "TA", "ATE", "EAT", "TEA", "ETA"]));
+        let mut board = Board::with_lexicon(lexicon);
+        board.write_across(String::from("AT"), 7, 7).unwrap();
+        board.submit().unwrap();
+        board
+    }
+
+    #[test]
+    fn test_moves_query() {
+        let board = board();
+        let tile_set = &board.rules().tile_set;
+        let parse = |line| MovesQuery::parse(args(line), tile_set, board.size());
+
+        let query = parse("3 tea row 9 using e").unwrap();
+        assert_eq!(query.count, 3);
+        assert_eq!(query.rack.unwrap().get_tiles_vec(), rack_for("AET").get_tiles_vec());
+        assert_eq!((query.across, query.line, query.using), (Some(true), Some(8), Some('E')));
+
+        let query = parse("col o down").unwrap();
+        assert_eq!((query.across, query.line), (Some(false), Some(14)));
+
+        // past the edge of the board
+        assert!(parse("row 16").unwrap_err().starts_with("No such row."));
+        assert!(parse("col P").unwrap_err().starts_with("No such column."));
+        assert!(parse("row").is_err());
+
+        // options in capitals are racks
+        let query = parse("ROW").unwrap();
+        assert_eq!(query.rack.unwrap().get_tiles_vec(), rack_for("ORW").get_tiles_vec());
+        assert_eq!((query.across, query.line), (None, None));
+        assert!(parse("ROW 9").is_err());
+        assert!(parse("ROW ACROSS").is_err());
+    }
+
+    #[test]
+    fn test_moves_filter() {
+        let board = board();
+        let tile_set = &board.rules().tile_set;
+        let rack = rack_for("TEA");
+        let find = |line| MovesQuery::parse(args(line), tile_set, board.size()).unwrap().find(&board, &rack);
+
+        let all = find("100");
+        assert!(all.len() > 2);
+        assert_eq!(find("2").len(), 2);
+        assert_eq!(find("2")[0].score, all[0].score);
+
+        let row = find("100 row 9");
+        assert!(!row.is_empty());
+        assert!(row.iter().all(|choice| choice.across && choice.row == 8));
+        let col = find("100 col H");
+        assert!(!col.is_empty());
+        assert!(col.iter().all(|choice| !choice.across && choice.col == 7));
+        let using = find("100 using e");
+        assert!(!using.is_empty());
+        assert!(using.iter().all(|choice| choice.tiles.contains(&'E')));
+    }
+
+    #[test]
+    fn test_format_move() {
+        let board = board();
+        let tile_set = &board.rules().tile_set;
+        let rack = rack_for("TA*");
+
+        // TA under AT forms AT and TA down
+        let choice = find_all_words(&board, &rack)
+            .into_vec()
+            .into_iter()
+            .find(|choice| choice.word == "TA" && choice.across && choice.row == 8 && choice.col == 7)
+            .unwrap();
+        assert_eq!(
+            format_move(&choice, &rack.get_tiles_vec(), tile_set),
+            format!("TA              9H     ACROSS {:>5}  TA       *        AT, TA", choice.score)
+        );
+    }
+
+    #[test]
+    fn test_challenge() {
+        let board = board();
+        let rack = Rack::new();
+        let view = GameView { board: &board, rack: &rack, bag_size: 0, opponents: 1, scoreless_turns: 0 };
+        let mut shell = Shell::new(&view, true);
+
+        // TA under AT forms AT and TA down, so the play stands
+        assert!(matches!(shell.exec_write_across(args("TA 9H")), ShellStatus::Continue));
+        assert!(matches!(shell.exec_challenge(), ShellStatus::Continue));
+        assert_eq!(shell.board.get_tile(8, 7), 'T');
+
+        // EE is no word, so it comes off the board
+        shell.exec_unstage();
+        assert!(matches!(shell.exec_write_across(args("EE 9H")), ShellStatus::Continue));
+        match shell.exec_challenge() {
+            ShellStatus::Withdrawn(play) => assert_eq!((play.word.as_str(), play.row, play.col, play.across), ("EE", 8, 7, true)),
+            _ => panic!("expected the play to be withdrawn"),
+        }
+        assert_eq!(shell.board.get_tile(8, 7), '-');
+    }
+
+    #[test]
+    fn test_check() {
+        let board = board();
+        let rack = Rack::new();
+        let view = GameView { board: &board, rack: &rack, bag_size: 0, opponents: 1, scoreless_turns: 0 };
+        let shell = Shell::new(&view, true);
+
+        let words = vec![String::from("TEA"), String::from("TAE"), String::from("EAT")];
+        assert_eq!(shell.check_words(&words), vec!["TEA is valid.", "TAE is not a word.", "EAT is valid."]);
+    }
+
+    #[test]
+    fn test_play() {
+        let board = board();
+        let rack = Rack::new();
+        let view = GameView { board: &board, rack: &rack, bag_size: 0, opponents: 1, scoreless_turns: 0 };
+
+        // the row first plays across, the column first plays down
+        let mut shell = Shell::new(&view, true);
+        match shell.exec_play(args("9H TA")) {
+            ShellStatus::Submit(play) => assert_eq!((play.word.as_str(), play.row, play.col, play.across), ("TA", 8, 7, true)),
+            _ => panic!("expected TA to be played across"),
+        }
+        let mut shell = Shell::new(&view, true);
+        match shell.exec_play(args("J7 TEA")) {
+            ShellStatus::Submit(play) => assert_eq!((play.word.as_str(), play.row, play.col, play.across), ("TEA", 6, 9, false)),
+            _ => panic!("expected TEA to be played down"),
+        }
+
+        let mut shell = Shell::new(&view, true);
+        match shell.exec_play(args("8H TA")) {
+            ShellStatus::Err(e) => assert_eq!(e, "Cannot play TA: 8H is already taken.\n"),
+            _ => panic!("expected 8H to be taken"),
+        }
+        match shell.exec_play(args("P1 TA")) {
+            ShellStatus::Err(e) => assert_eq!(e, "Error: square 'P1' is off the board\n"),
+            _ => panic!("expected P1 to be off the board"),
+        }
     }
 }
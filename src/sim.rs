@@ -0,0 +1,272 @@
+// Monte Carlo simulation. The best few plays by equity are each tried against
+// many random opponent racks drawn from the unseen tiles, one for each
+// opponent, with everyone then playing a few more turns by equity. The play
+// with the best average spread over the best opponent at the end wins.
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::equity::{choose_move, equity, leave_after, Decision, LeaveTable};
+use crate::game::{Bag, Board, Rack, RACK_SIZE};
+use crate::greedy::{find_all_words, WordChoice};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    // how many of the best plays by equity to simulate.
+    pub candidates: usize,
+    // how many opponent racks to try each play against.
+    pub iterations: usize,
+    // turns played after the candidate, alternating with the opponent.
+    pub plies: usize,
+    // stop early once this much time has passed.
+    pub time_budget: Duration,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig { candidates: 10, iterations: 100, plies: 2, time_budget: Duration::from_secs(5) }
+    }
+}
+
+// The tiles this rack's owner cannot see: the bag and every other rack. They
+// are sorted, so that simulations only depend on their own rng.
+pub fn unseen_tiles(board: &Board, rack: &Rack) -> Vec<char> {
+    let mut unseen = board.rules().tile_set.tiles();
+    for tile in board.played_tiles().into_iter().chain(rack.get_tiles_vec()) {
+        if let Some(i) = unseen.iter().position(|&t| t == tile) {
+            unseen.swap_remove(i);
+        }
+    }
+    unseen.sort();
+    unseen
+}
+
+pub fn simulate<R: Rng>(
+    board: &Board,
+    rack: &Rack,
+    opponents: usize,
+    config: &SimConfig,
+    leaves: &LeaveTable,
+    rng: &mut R,
+) -> Decision {
+    let unseen = unseen_tiles(board, rack);
+    let bag_size = unseen.len().saturating_sub(RACK_SIZE * opponents);
+
+    let tiles = rack.get_tiles_vec();
+    let mut candidates: Vec<(WordChoice, f64)> = find_all_words(board, rack)
+        .into_iter()
+        .map(|choice| {
+            let value = equity(choice.score, &leave_after(&tiles, &choice.tiles), bag_size, leaves, board.rules());
+            (choice, value)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    candidates.truncate(config.candidates);
+
+    // Only plays are simulated. If the static evaluation would rather
+    // exchange, or there is nothing to choose between, go with it.
+    let fallback = choose_move(board, rack, bag_size, leaves);
+    if !matches!(fallback, Decision::Play(_)) || candidates.len() < 2 || config.iterations == 0 {
+        return fallback;
+    }
+
+    let mut candidates: Vec<WordChoice> = candidates.into_iter().map(|(choice, _)| choice).collect();
+    let (totals, _) = play_outs(board, rack, opponents, &candidates, config, leaves, rng);
+    let best = (0..candidates.len())
+        .max_by(|&a, &b| totals[a].total_cmp(&totals[b]))
+        .expect("there are at least two candidates");
+    Decision::Play(candidates.swap_remove(best))
+}
+
+// Plays every candidate out against the same draws until the iterations or
+// the time budget run out, but always at least once. Returns the total spread
+// of each candidate and how many iterations there were.
+fn play_outs<R: Rng>(
+    board: &Board,
+    rack: &Rack,
+    opponents: usize,
+    candidates: &[WordChoice],
+    config: &SimConfig,
+    leaves: &LeaveTable,
+    rng: &mut R,
+) -> (Vec<f64>, usize) {
+    let unseen = unseen_tiles(board, rack);
+    let start = Instant::now();
+    let mut totals = vec![0.0; candidates.len()];
+    let mut iterations = 0;
+
+    while iterations < config.iterations && (iterations == 0 || start.elapsed() < config.time_budget) {
+        // every candidate faces the same draw, so the comparison is fair
+        let mut draw = unseen.clone();
+        draw.shuffle(rng);
+
+        for (total, choice) in totals.iter_mut().zip(candidates) {
+            *total += play_out(board, rack, opponents, choice, draw.clone(), config.plies, leaves);
+        }
+        iterations += 1;
+    }
+    (totals, iterations)
+}
+
+// Deals each opponent a rack off the top of the shuffled tiles. The rest
+// are the bag.
+fn deal(draw: &mut Vec<char>, opponents: usize) -> Vec<Rack> {
+    let mut racks = vec![Rack::new(); opponents];
+    for rack in &mut racks {
+        for _ in 0..RACK_SIZE {
+            if let Some(tile) = draw.pop() {
+                rack.add_tile(tile);
+            }
+        }
+    }
+    racks
+}
+
+// Plays choice and then plies more turns, going round the table. Returns the
+// spread from the point of view of the player making choice over the best
+// opponent, counting the points scored and the value of the leaves.
+fn play_out(
+    board: &Board,
+    rack: &Rack,
+    opponents: usize,
+    choice: &WordChoice,
+    mut draw: Vec<char>,
+    plies: usize,
+    leaves: &LeaveTable,
+) -> f64 {
+    let mut board = board.clone();
+    let mut racks = vec![rack.clone()];
+    racks.extend(deal(&mut draw, opponents));
+    let mut bag = Bag::from_tiles(draw);
+
+    let mut points = vec![0.0; racks.len()];
+    points[0] = choice.score as f64;
+    let placed = if choice.across {
+        board.write_across_from_rack(&mut racks[0], choice.word.clone(), choice.row, choice.col)
+    } else {
+        board.write_down_from_rack(&mut racks[0], choice.word.clone(), choice.row, choice.col)
+    };
+    if placed.and_then(|()| board.submit()).is_err() {
+        return f64::NEG_INFINITY;
+    }
+    racks[0].draw(&mut bag);
+
+    for ply in 0..plies {
+        let mover = (ply + 1) % racks.len();
+        if racks.iter().any(Rack::is_empty) {
+            break;
+        }
+
+        let score = match choose_move(&board, &racks[mover], bag.size(), leaves) {
+            Decision::Play(reply) => {
+                let placed = if reply.across {
+                    board.write_across_from_rack(&mut racks[mover], reply.word, reply.row, reply.col)
+                } else {
+                    board.write_down_from_rack(&mut racks[mover], reply.word, reply.row, reply.col)
+                };
+                let score = placed.and_then(|()| board.submit()).unwrap_or(0);
+                racks[mover].draw(&mut bag);
+                score
+            }
+            Decision::Exchange(tiles) => {
+                racks[mover].swap(&mut bag, tiles);
+                0
+            }
+            Decision::Pass => 0,
+        };
+
+        points[mover] += score as f64;
+    }
+
+    for (points, rack) in points.iter_mut().zip(&racks) {
+        *points += equity(0, &rack.get_tiles_vec(), bag.size(), leaves, board.rules());
+    }
+    let best_opponent = points[1..].iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    points[0] - best_opponent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use std::sync::Arc;
+    use crate::lexicon::Lexicon;
+
+    #[test]
+    fn test_unseen_tiles() {
+        let mut board = Board::new("dict.txt".to_string());
+        board.write_across(String::from("QI"), 7, 7).unwrap();
+        board.submit().unwrap();
+
+        let unseen = unseen_tiles(&board, &Rack::from_tiles("AEIRST*"));
+        assert_eq!(unseen.len(), 100 - 2 - 7);
+        assert_eq!(unseen.iter().filter(|&&tile| tile == 'Q').count(), 0);
+        assert_eq!(unseen.iter().filter(|&&tile| tile == '*').count(), 1);
+    }
+
+    #[test]
+    fn test_simulate_picks_a_legal_play() {
+        let board = Board::new("dict.txt".to_string());
+        let rack = Rack::from_tiles("AEINRST");
+        let config = SimConfig { candidates: 3, iterations: 4, plies: 2, ..SimConfig::default() };
+
+        let mut rng = StdRng::seed_from_u64(7);
+        match simulate(&board, &rack, 1, &config, &LeaveTable::default(), &mut rng) {
+            Decision::Play(choice) => {
+                let mut board = board.clone();
+                let mut rack = rack.clone();
+                if choice.across {
+                    board.write_across_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col).unwrap();
+                } else {
+                    board.write_down_from_rack(&mut rack, choice.word.clone(), choice.row, choice.col).unwrap();
+                }
+                assert_eq!(board.submit().unwrap(), choice.score);
+            }
+            other => panic!("expected a play, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_one_rack_per_opponent() {
+        let mut draw: Vec<char> = "ABCDEFGHIJKLMNOPQRST".chars().collect();
+        let racks = deal(&mut draw, 2);
+        assert_eq!(racks.len(), 2);
+        assert!(racks.iter().all(|rack| rack.len() == RACK_SIZE));
+        assert_eq!(draw.len(), 20 - 2 * RACK_SIZE);
+
+        let board = Board::new("dict.txt".to_string());
+        let config = SimConfig { candidates: 3, iterations: 2, plies: 3, ..SimConfig::default() };
+        let mut rng = StdRng::seed_from_u64(3);
+        let decision = simulate(&board, &Rack::from_tiles("AEINRST"), 3, &config, &LeaveTable::default(), &mut rng);
+        assert!(matches!(decision, Decision::Play(_)));
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let board = Board::new("dict.txt".to_string());
+        let rack = Rack::from_tiles("AEINRST");
+        let candidates = find_all_words(&board, &rack).sorted_by_score().top(2).into_vec();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // out of time straight away, but every candidate is still played out once
+        let config = SimConfig { iterations: 1_000_000, time_budget: Duration::ZERO, ..SimConfig::default() };
+        let (totals, iterations) = play_outs(&board, &rack, 1, &candidates, &config, &LeaveTable::default(), &mut rng);
+        assert_eq!(iterations, 1);
+        assert_eq!(totals.len(), 2);
+
+        let config = SimConfig { iterations: 3, ..SimConfig::default() };
+        let (_, iterations) = play_outs(&board, &rack, 1, &candidates, &config, &LeaveTable::default(), &mut rng);
+        assert_eq!(iterations, 3);
+    }
+
+    #[test]
+    fn test_falls_back_to_equity() {
+        let board = Board::with_lexicon(Arc::new(Lexicon::from_words(["QI"])));
+        let mut rng = StdRng::seed_from_u64(1);
+        let decision = simulate(&board, &Rack::from_tiles("QI"), 1, &SimConfig::default(), &LeaveTable::default(), &mut rng);
+        assert!(matches!(decision, Decision::Play(choice) if choice.word == "QI"));
+    }
+}
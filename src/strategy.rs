@@ -0,0 +1,209 @@
+// How players decide what to do. A strategy is shown the game from its
+// player's seat and answers with a move. Player makes the move, so a
+// strategy never changes the board, bag or rack itself and new bots can be
+// added without touching the game loop.
+
+use std::sync::Arc;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::endgame::{deduce_opponent_rack, EndgameConfig, EndgameSolver};
+use crate::equity::{choose_move, Decision, LeaveTable};
+use crate::game::{Board, Rack};
+use crate::sim::{simulate, SimConfig};
+
+// What a player can see on their turn.
+pub struct GameView<'a> {
+    pub board: &'a Board,
+    // empty for players without a rack.
+    pub rack: &'a Rack,
+    pub bag_size: usize,
+    // how many other players are in the game.
+    pub opponents: usize,
+    // turns in a row without points. The game ends after MAX_SCORELESS_TURNS.
+    pub scoreless_turns: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Move {
+    // the whole main word, with blanks in lowercase.
+    Play { word: String, row: usize, col: usize, across: bool },
+    // the tiles to put back in the bag.
+    Exchange(Vec<char>),
+    Pass,
+}
+
+// What a person at the keyboard can ask of the session instead of moving.
+// None of these end the turn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    // save or load the game.
+    Save(String),
+    Load(String),
+    // take back the last turn, or play a turn that was taken back again.
+    Undo,
+    Redo,
+    Exit,
+}
+
+// A strategy's answer on its turn. Bots only ever move.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Turn {
+    Move(Move),
+    // a play entered for a player without a rack that was challenged off the
+    // board. It scores nothing and the turn is lost.
+    Withdrawn { word: String, row: usize, col: usize, across: bool },
+    Command(Command),
+}
+
+pub trait Strategy {
+    // written to saved games to say who was playing, e.g. "cpu".
+    fn name(&self) -> &str;
+
+    // Players without a rack are not dealt tiles and may play any tiles they
+    // like, e.g. to record a game played on a real board.
+    fn rackless(&self) -> bool {
+        false
+    }
+
+    // Moves come from the person at the keyboard. Undo goes back to their
+    // turn rather than to a bot's, which would just play again.
+    fn interactive(&self) -> bool {
+        false
+    }
+
+    fn choose(&mut self, view: &GameView) -> Turn;
+}
+
+impl From<Decision> for Move {
+    fn from(decision: Decision) -> Self {
+        match decision {
+            Decision::Play(choice) => {
+                Move::Play { word: choice.word, row: choice.row, col: choice.col, across: choice.across }
+            }
+            Decision::Exchange(tiles) => Move::Exchange(tiles),
+            Decision::Pass => Move::Pass,
+        }
+    }
+}
+
+// The computer player. Takes the play with the best equity, or simulates the
+// best few when it has a sim config, and searches the endgame exactly once
+// the bag is empty.
+#[derive(Clone, Debug)]
+pub struct CpuBot {
+    // how the bot values the tiles it keeps.
+    pub leaves: Arc<LeaveTable>,
+    pub sim: Option<SimConfig>,
+    // how to search once the bag is empty. None turns it off.
+    pub endgame: Option<EndgameConfig>,
+    // draws the racks for simulations.
+    pub rng: StdRng,
+}
+
+impl Default for CpuBot {
+    fn default() -> Self {
+        CpuBot {
+            leaves: Arc::new(LeaveTable::default()),
+            sim: None,
+            endgame: Some(EndgameConfig::default()),
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
+}
+
+impl Strategy for CpuBot {
+    fn name(&self) -> &str {
+        if self.sim.is_some() { "sim" } else { "cpu" }
+    }
+
+    fn choose(&mut self, view: &GameView) -> Turn {
+        let opponent = deduce_opponent_rack(view.board, view.rack, view.bag_size, view.opponents);
+        let decision = match (&self.endgame, opponent, &self.sim) {
+            (Some(endgame), Some(opponent), _) => {
+                let result = EndgameSolver::new(endgame.clone()).solve(view.board, view.rack, &opponent, view.scoreless_turns);
+                match result.line.into_iter().next().flatten() {
+                    Some(choice) => Decision::Play(choice),
+                    None => Decision::Pass,
+                }
+            }
+            (_, _, Some(config)) => simulate(view.board, view.rack, view.opponents, config, &self.leaves, &mut self.rng),
+            (_, _, None) => choose_move(view.board, view.rack, view.bag_size, &self.leaves),
+        };
+        Turn::Move(decision.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Bag;
+    use crate::player::{Player, TurnResult};
+    use crate::session::Session;
+
+    // plays a fixed list of moves, then passes. Like a person, it is asked
+    // again after a move that cannot be made.
+    struct Scripted(Vec<Move>);
+
+    impl Strategy for Scripted {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn interactive(&self) -> bool {
+            true
+        }
+
+        fn choose(&mut self, _view: &GameView) -> Turn {
+            Turn::Move(if self.0.is_empty() { Move::Pass } else { self.0.remove(0) })
+        }
+    }
+
+    #[test]
+    fn test_custom_strategy() {
+        let moves = vec![
+            Move::Play { word: String::from("QI"), row: 7, col: 7, across: true },
+            // not on the rack any more, so this is refused and the script passes
+            Move::Play { word: String::from("QIS"), row: 7, col: 7, across: true },
+        ];
+        let mut bag = Bag::new();
+        bag.tiles.clear();
+        let players = vec![Player::new(1, Box::new(Scripted(moves)))];
+        let mut session = Session::new(Board::new("dict.txt".to_string()), bag, players);
+        session.players[0].rack = Rack::from_tiles("QIE");
+
+        assert!(matches!(session.play_turn(), TurnResult::Score(22)));
+        assert!(matches!(session.play_turn(), TurnResult::Pass));
+        assert_eq!(session.players[0].score, 22);
+        assert_eq!(session.players[0].rack.get_tiles_vec(), vec!['E']);
+    }
+
+    // a bot that plays tiles it does not have.
+    struct Broken;
+
+    impl Strategy for Broken {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn choose(&mut self, _view: &GameView) -> Turn {
+            Turn::Move(Move::Play { word: String::from("QIS"), row: 7, col: 7, across: true })
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "chose a move it cannot make")]
+    fn test_illegal_bot_move() {
+        let mut player = Player::new(1, Box::new(Broken));
+        player.rack = Rack::from_tiles("QIE");
+        let mut bag = Bag::new();
+        player.play_turn(&mut Board::new("dict.txt".to_string()), &mut bag, 1, 0);
+    }
+
+    #[test]
+    fn test_cpu_names() {
+        assert_eq!(CpuBot::default().name(), "cpu");
+        assert_eq!(CpuBot { sim: Some(SimConfig::default()), ..CpuBot::default() }.name(), "sim");
+    }
+}
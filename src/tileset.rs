@@ -0,0 +1,421 @@
+// Tile sets: which tiles go in the bag and what each letter is worth. Tile
+// sets are plain text, one letter per line with how many of it are in a full
+// bag and its value:
+//
+//   # letter count value
+//   A 9 1
+//   B 2 3
+//   ...
+//   * 2 0
+//
+// '*' is the blank, which is always worth nothing. A tile can also be a
+// letter outside A to Z, like the Spanish Ñ, or several letters, like CH.
+// Such a tile is stored as a single stand-in char (see DIGRAPH_CODES) and is
+// only ever shown by its name, so word lists, racks and plays are run
+// through encode and decode on their way in and out.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const STANDARD: &str = "
+A 9 1
+B 2 3
+C 2 3
+D 4 2
+E 12 1
+F 2 4
+G 3 2
+H 2 4
+I 9 1
+J 1 8
+K 1 5
+L 4 1
+M 2 3
+N 6 1
+O 8 1
+P 2 3
+Q 1 10
+R 6 1
+S 4 1
+T 6 1
+U 4 1
+V 2 4
+W 2 4
+X 1 8
+Y 2 4
+Z 1 10
+* 2 0
+";
+
+// Words With Friends.
+const WWF: &str = "
+A 9 1
+B 2 4
+C 2 4
+D 5 2
+E 13 1
+F 2 4
+G 3 3
+H 4 3
+I 8 1
+J 1 10
+K 1 5
+L 4 2
+M 2 4
+N 5 2
+O 8 1
+P 2 4
+Q 1 10
+R 6 1
+S 5 1
+T 7 1
+U 4 2
+V 2 5
+W 2 4
+X 1 8
+Y 2 3
+Z 1 10
+* 2 0
+";
+
+// twice as many tiles as the standard set, for the 21x21 board.
+const SUPER: &str = "
+A 16 1
+B 4 3
+C 6 3
+D 8 2
+E 24 1
+F 4 4
+G 5 2
+H 5 4
+I 13 1
+J 2 8
+K 2 5
+L 7 1
+M 6 3
+N 13 1
+O 15 1
+P 4 3
+Q 2 10
+R 13 1
+S 10 1
+T 15 1
+U 7 1
+V 3 4
+W 4 4
+X 2 8
+Y 4 4
+Z 2 10
+* 4 0
+";
+
+// Spanish, with the CH, LL, RR and Ñ tiles.
+const SPANISH: &str = "
+A 12 1
+B 2 3
+C 4 3
+CH 1 5
+D 5 2
+E 12 1
+F 1 4
+G 2 2
+H 2 4
+I 6 1
+J 1 8
+L 4 1
+LL 1 8
+M 2 3
+N 5 1
+Ñ 1 8
+O 9 1
+P 2 3
+Q 1 5
+R 5 1
+RR 1 8
+S 6 1
+T 4 1
+U 5 1
+V 1 4
+X 1 8
+Y 1 4
+Z 1 10
+* 2 0
+";
+
+// the tile sets that can be chosen by name.
+pub const BUILT_IN: [&str; 4] = ["standard", "wwf", "super", "spanish"];
+
+// Stand-ins for tiles of more than one letter, given out in order of the
+// tiles' names. Letters that are tiles in their own right are skipped.
+const DIGRAPH_CODES: [char; 12] = ['Б', 'Г', 'Д', 'Ж', 'И', 'Л', 'П', 'Ф', 'Ц', 'Ч', 'Ш', 'Щ'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Letter {
+    pub letter: char,
+    // how many are in a full bag.
+    pub count: usize,
+    pub value: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileSet {
+    // in alphabetical order of their names.
+    letters: Vec<Letter>,
+    // the names of tiles of more than one letter, by their stand-in.
+    digraphs: Vec<(char, String)>,
+    pub blanks: usize,
+}
+
+impl Default for TileSet {
+    fn default() -> Self {
+        TileSet::standard()
+    }
+}
+
+impl TileSet {
+    pub fn standard() -> Self {
+        TileSet::parse(STANDARD).expect("The standard tile set is valid.")
+    }
+
+    pub fn wwf() -> Self {
+        TileSet::parse(WWF).expect("The WWF tile set is valid.")
+    }
+
+    pub fn super_sized() -> Self {
+        TileSet::parse(SUPER).expect("The super tile set is valid.")
+    }
+
+    pub fn spanish() -> Self {
+        TileSet::parse(SPANISH).expect("The Spanish tile set is valid.")
+    }
+
+    // a built-in tile set by name, see BUILT_IN.
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(TileSet::standard()),
+            "wwf" => Some(TileSet::wwf()),
+            "super" => Some(TileSet::super_sized()),
+            "spanish" => Some(TileSet::spanish()),
+            _ => None,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        TileSet::parse(&fs::read_to_string(path)?)
+    }
+
+    // A built-in tile set if there is one with this name, otherwise the tile
+    // set in the file at this path.
+    pub fn load(name_or_path: &str) -> io::Result<Self> {
+        match TileSet::built_in(name_or_path) {
+            Some(tile_set) => Ok(tile_set),
+            None => TileSet::from_file(name_or_path),
+        }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        // names and counts first, since stand-ins can only be given out once
+        // every single letter tile is known
+        let mut named: Vec<(String, usize, i32)> = Vec::new();
+        let mut blanks = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = || invalid_data(&format!("bad tile set line {}: '{line}'", number + 1));
+
+            let [name, count, value] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(bad_line());
+            };
+            let count: usize = count.parse().map_err(|_| bad_line())?;
+            let value: i32 = value.parse().map_err(|_| bad_line())?;
+
+            if name == "*" {
+                if value != 0 {
+                    return Err(invalid_data("blanks are worth nothing"));
+                }
+                if blanks.replace(count).is_some() {
+                    return Err(invalid_data("the blanks are listed twice"));
+                }
+            } else if !name.chars().all(char::is_uppercase) {
+                return Err(invalid_data(&format!("'{name}' is not made of uppercase letters")));
+            } else if named.iter().any(|(known, _, _)| known == name) {
+                return Err(invalid_data(&format!("{name} is listed twice")));
+            } else {
+                named.push((name.to_string(), count, value));
+            }
+        }
+
+        if named.is_empty() {
+            return Err(invalid_data("the tile set has no letters"));
+        }
+        named.sort();
+
+        let singles: Vec<char> = named.iter().filter_map(|(name, _, _)| single_char(name)).collect();
+        let mut codes = DIGRAPH_CODES.into_iter().filter(|code| !singles.contains(code));
+        let mut letters = Vec::new();
+        let mut digraphs = Vec::new();
+        for (name, count, value) in named {
+            let letter = match single_char(&name) {
+                Some(letter) => letter,
+                None => {
+                    let code = codes.next().ok_or_else(|| invalid_data("too many tiles of more than one letter"))?;
+                    digraphs.push((code, name));
+                    code
+                }
+            };
+            letters.push(Letter { letter, count, value });
+        }
+
+        Ok(TileSet { letters, digraphs, blanks: blanks.unwrap_or(0) })
+    }
+
+    pub fn letters(&self) -> &[Letter] {
+        &self.letters
+    }
+
+    // The tiles of more than one letter, with the chars that stand in for them.
+    pub fn digraphs(&self) -> impl Iterator<Item = (char, &str)> {
+        self.digraphs.iter().map(|(code, name)| (*code, name.as_str()))
+    }
+
+    // Blanks score nothing, whether still on a rack ('*') or played as a
+    // letter (written in lowercase). So do letters that are not in the set.
+    pub fn score_letter(&self, letter: char) -> i32 {
+        self.letters.iter().find(|known| known.letter == letter).map_or(0, |known| known.value)
+    }
+
+    // every tile in a full bag, in alphabetical order with the blanks last.
+    pub fn tiles(&self) -> Vec<char> {
+        let mut tiles: Vec<char> = self
+            .letters
+            .iter()
+            .flat_map(|known| std::iter::repeat_n(known.letter, known.count))
+            .collect();
+        tiles.extend(std::iter::repeat_n('*', self.blanks));
+        tiles
+    }
+
+    // How a tile is shown, e.g. "CH", or "ch" when a blank stands in for it.
+    // Other chars are shown as they are.
+    pub fn name(&self, tile: char) -> String {
+        let upper = tile.to_uppercase().next().unwrap_or(tile);
+        match self.digraphs.iter().find(|(code, _)| *code == upper) {
+            Some((_, name)) if tile.is_lowercase() => name.to_lowercase(),
+            Some((_, name)) => name.clone(),
+            None => tile.to_string(),
+        }
+    }
+
+    // the names of all the tiles in s, run together.
+    pub fn decode(&self, s: &str) -> String {
+        s.chars().map(|tile| self.name(tile)).collect()
+    }
+
+    // The inverse of decode: replaces the names of tiles of more than one
+    // letter with their stand-ins, the longest name first. Lowercase names
+    // are blanks. Everything else is kept as it is.
+    pub fn encode(&self, s: &str) -> String {
+        let mut names: Vec<(String, char)> = Vec::new();
+        for (code, name) in &self.digraphs {
+            names.push((name.clone(), *code));
+            names.push((name.to_lowercase(), code.to_lowercase().next().unwrap_or(*code)));
+        }
+        names.sort_by_key(|(name, _)| std::cmp::Reverse(name.chars().count()));
+
+        let mut result = String::new();
+        let mut rest = s;
+        'outer: while let Some(next) = rest.chars().next() {
+            for (name, code) in &names {
+                if let Some(after) = rest.strip_prefix(name.as_str()) {
+                    result.push(*code);
+                    rest = after;
+                    continue 'outer;
+                }
+            }
+            result.push(next);
+            rest = &rest[next.len_utf8()..];
+        }
+        result
+    }
+}
+
+fn single_char(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), None) => Some(letter),
+        _ => None,
+    }
+}
+
+// Writes the tile set in the format parse reads.
+impl fmt::Display for TileSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for known in &self.letters {
+            writeln!(f, "{} {} {}", self.name(known.letter), known.count, known.value)?;
+        }
+        writeln!(f, "* {} 0", self.blanks)
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in() {
+        let standard = TileSet::standard();
+        assert_eq!(standard.tiles().len(), 100);
+        assert_eq!(standard.score_letter('Q'), 10);
+        assert_eq!(standard.score_letter('q'), 0);
+        assert_eq!(standard.score_letter('*'), 0);
+        assert_eq!(TileSet::parse(&standard.to_string()).unwrap(), standard);
+
+        assert_eq!(TileSet::built_in("wwf").unwrap().tiles().len(), 104);
+        assert_eq!(TileSet::built_in("super").unwrap().tiles().len(), 200);
+        assert!(TileSet::built_in("nope").is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        let tile_set = TileSet::parse("# house rules\nZ 3 1\nA 2 5\n* 1 0\n").unwrap();
+        assert_eq!(tile_set.tiles(), vec!['A', 'A', 'Z', 'Z', 'Z', '*']);
+        assert_eq!(tile_set.score_letter('A'), 5);
+        assert_eq!(tile_set.score_letter('B'), 0);
+
+        assert_eq!(TileSet::parse("A 2\n").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(TileSet::parse("A 2 1\nA 3 1\n").is_err());
+        assert!(TileSet::parse("a 2 1\n").is_err());
+        assert!(TileSet::parse("Ch 2 1\n").is_err());
+        assert!(TileSet::parse("* 2 3\nA 1 1\n").is_err());
+        assert!(TileSet::parse("* 2 0\n").is_err());
+    }
+
+    #[test]
+    fn test_digraphs() {
+        let spanish = TileSet::built_in("spanish").unwrap();
+        assert_eq!(spanish.tiles().len(), 100);
+        assert_eq!(TileSet::parse(&spanish.to_string()).unwrap(), spanish);
+
+        let word = spanish.encode("CHILLAR");
+        assert_eq!(word.chars().count(), 5);
+        assert_eq!(spanish.decode(&word), "CHILLAR");
+        assert_eq!(spanish.score_letter(word.chars().next().unwrap()), 5);
+        assert_eq!(spanish.score_letter('Ñ'), 8);
+
+        // a blank standing in for LL
+        let blank = spanish.encode("ll");
+        assert_eq!(blank.chars().count(), 1);
+        assert!(blank.chars().all(char::is_lowercase));
+        assert_eq!(spanish.name(blank.chars().next().unwrap()), "ll");
+        assert_eq!(spanish.decode(&spanish.encode("ÑU*RR")), "ÑU*RR");
+    }
+}
@@ -0,0 +1,250 @@
+// Headless matches between two bots. Games are played in pairs from the same
+// seeded deal, once with each bot going first, so that neither a lucky draw
+// nor the first move favours one side. The results are summed up per bot.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::game::{Bag, Board, RACK_SIZE};
+use crate::lexicon::Lexicon;
+use crate::player::{Player, TurnResult};
+use crate::rules::Rules;
+use crate::session::Session;
+use crate::strategy::{Command, Strategy};
+
+// for 95% confidence intervals.
+const Z: f64 = 1.96;
+
+pub struct Contestant<'a> {
+    pub name: String,
+    // makes a fresh strategy for each game from a seed for its random choices.
+    pub strategy: &'a dyn Fn(u64) -> Box<dyn Strategy>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    pub ties: usize,
+    pub total_score: i64,
+    pub total_spread: i64,
+    // sum of the squared spreads, for the spread's variance.
+    pub total_spread_squared: i64,
+    pub bingos: usize,
+    pub moves: usize,
+    pub move_time: Duration,
+}
+
+impl Stats {
+    // ties count as half a win.
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + 0.5 * self.ties as f64) / self.games as f64
+    }
+
+    pub fn win_rate_interval(&self) -> f64 {
+        if self.games == 0 {
+            return 0.0;
+        }
+        let rate = self.win_rate();
+        Z * (rate * (1.0 - rate) / self.games as f64).sqrt()
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        self.per_game(self.total_score as f64)
+    }
+
+    pub fn mean_spread(&self) -> f64 {
+        self.per_game(self.total_spread as f64)
+    }
+
+    pub fn spread_interval(&self) -> f64 {
+        if self.games < 2 {
+            return 0.0;
+        }
+        let n = self.games as f64;
+        let mean = self.mean_spread();
+        let variance = (self.total_spread_squared as f64 - n * mean * mean) / (n - 1.0);
+        Z * (variance.max(0.0) / n).sqrt()
+    }
+
+    pub fn bingos_per_game(&self) -> f64 {
+        self.per_game(self.bingos as f64)
+    }
+
+    pub fn mean_move_time(&self) -> Duration {
+        if self.moves == 0 {
+            return Duration::ZERO;
+        }
+        self.move_time / self.moves as u32
+    }
+
+    fn per_game(&self, total: f64) -> f64 {
+        if self.games == 0 { 0.0 } else { total / self.games as f64 }
+    }
+
+    fn add_game(&mut self, score: i32, opponent_score: i32) {
+        let spread = (score - opponent_score) as i64;
+        self.games += 1;
+        self.total_score += score as i64;
+        self.total_spread += spread;
+        self.total_spread_squared += spread * spread;
+        if spread > 0 {
+            self.wins += 1;
+        } else if spread == 0 {
+            self.ties += 1;
+        }
+    }
+}
+
+// Plays games between two contestants, alternating who goes first. Game i
+// is dealt from seed + i / 2, so the same seed plays the same games.
+pub fn run(lexicon: Arc<Lexicon>, rules: Arc<Rules>, contestants: [Contestant; 2], games: usize, seed: u64) -> [Stats; 2] {
+    let mut stats = contestants.each_ref().map(|contestant| Stats { name: contestant.name.clone(), ..Stats::default() });
+
+    for game in 0..games {
+        let deal = seed.wrapping_add(game as u64 / 2);
+
+        // seats[i] is the contestant sitting in seat i
+        let seats = if game % 2 == 0 { [0, 1] } else { [1, 0] };
+        let players = (1..)
+            .zip(seats)
+            .map(|(id, contestant)| {
+                let bot_seed = deal.wrapping_mul(2).wrapping_add(contestant as u64);
+                let mut player = Player::new(id, (contestants[contestant].strategy)(bot_seed));
+                player.quiet = true;
+                player
+            })
+            .collect();
+        let mut session = Session::new(Board::with_rules(lexicon.clone(), rules.clone()), Bag::with_rules(&rules, deal), players);
+
+        while !session.is_over() {
+            let contestant = seats[session.turn];
+            let played = session.board.played_tiles().len();
+
+            let start = Instant::now();
+            let result = session.play_turn();
+            stats[contestant].move_time += start.elapsed();
+            stats[contestant].moves += 1;
+
+            if matches!(result, TurnResult::Command(Command::Exit)) {
+                break;
+            }
+            if session.board.played_tiles().len() - played == RACK_SIZE {
+                stats[contestant].bingos += 1;
+            }
+        }
+
+        let standings = session.finish();
+        let scores = standings.standings.iter().map(|standing| standing.score).collect::<Vec<_>>();
+        for (seat, &contestant) in seats.iter().enumerate() {
+            stats[contestant].add_game(scores[seat], scores[1 - seat]);
+        }
+    }
+
+    stats
+}
+
+pub fn write_table<W: Write>(stats: &[Stats], writer: &mut W) -> io::Result<()> {
+    writeln!(
+        writer,
+        "{:<12} {:>6} {:>6} {:>6} {:>16} {:>7} {:>17} {:>7} {:>9}",
+        "Player", "Games", "Wins", "Ties", "Win %", "Score", "Spread", "Bingos", "ms/move"
+    )?;
+    for stat in stats {
+        writeln!(
+            writer,
+            "{:<12} {:>6} {:>6} {:>6} {:>16} {:>7.1} {:>17} {:>7.2} {:>9.1}",
+            stat.name,
+            stat.games,
+            stat.wins,
+            stat.ties,
+            format!("{:.1} ± {:.1}", 100.0 * stat.win_rate(), 100.0 * stat.win_rate_interval()),
+            stat.mean_score(),
+            format!("{:+.1} ± {:.1}", stat.mean_spread(), stat.spread_interval()),
+            stat.bingos_per_game(),
+            stat.mean_move_time().as_secs_f64() * 1000.0,
+        )?;
+    }
+    Ok(())
+}
+
+// One row per contestant, with the ± figures as 95% confidence half-widths.
+pub fn write_csv<W: Write>(stats: &[Stats], writer: &mut W) -> io::Result<()> {
+    writeln!(
+        writer,
+        "player,games,wins,ties,win_rate,win_rate_ci,mean_score,mean_spread,spread_ci,bingos_per_game,ms_per_move"
+    )?;
+    for stat in stats {
+        writeln!(
+            writer,
+            "{},{},{},{},{:.4},{:.4},{:.2},{:.2},{:.2},{:.3},{:.3}",
+            stat.name,
+            stat.games,
+            stat.wins,
+            stat.ties,
+            stat.win_rate(),
+            stat.win_rate_interval(),
+            stat.mean_score(),
+            stat.mean_spread(),
+            stat.spread_interval(),
+            stat.bingos_per_game(),
+            stat.mean_move_time().as_secs_f64() * 1000.0,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::CpuBot;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::default();
+        stats.add_game(400, 300);
+        stats.add_game(300, 400);
+        stats.add_game(350, 350);
+        stats.add_game(450, 350);
+
+        assert_eq!((stats.games, stats.wins, stats.ties), (4, 2, 1));
+        assert_eq!(stats.win_rate(), 0.625);
+        assert_eq!(stats.mean_score(), 375.0);
+        assert_eq!(stats.mean_spread(), 25.0);
+        assert!(stats.spread_interval() > 0.0);
+    }
+
+    #[test]
+    fn test_tournament() {
+        let lexicon = Arc::new(Lexicon::from_file("dict.txt").unwrap());
+        let cpu = |_| Box::new(CpuBot { endgame: None, ..CpuBot::default() }) as Box<dyn Strategy>;
+        let contestants = || {
+            [
+                Contestant { name: String::from("a"), strategy: &cpu },
+                Contestant { name: String::from("b"), strategy: &cpu },
+            ]
+        };
+
+        let stats = run(lexicon.clone(), Arc::new(Rules::standard()), contestants(), 2, 1);
+        assert_eq!(stats[0].games, 2);
+        assert_eq!(stats[0].wins + stats[0].ties + stats[1].wins, 2);
+        // one bot's spread is the other's loss
+        assert_eq!(stats[0].total_spread, -stats[1].total_spread);
+        assert!(stats[0].moves > 0);
+
+        // the same seed plays the same games
+        let again = run(lexicon, Arc::new(Rules::standard()), contestants(), 2, 1);
+        assert_eq!(again[0].total_score, stats[0].total_score);
+        assert_eq!(again[1].total_score, stats[1].total_score);
+        assert_eq!(again[0].moves, stats[0].moves);
+
+        let mut csv = Vec::new();
+        write_csv(&stats, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 3);
+    }
+}